@@ -0,0 +1,155 @@
+use super::*;
+
+// proven result of a root move, for the side to move at the root
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Proven {
+    Win,
+    Loss,
+    Draw,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PvLine {
+    #[serde(rename = "move")]
+    pub mv: Move,
+    pub visits: f32,
+    // mean value for the side to move at the root
+    pub value: f32,
+    pub proven: Proven,
+    // starts with `mv`
+    pub pv: Vec<Move>,
+}
+
+impl SearchNode {
+    pub fn proven(&self) -> Proven {
+        match self.data {
+            // the child is scored for the opponent
+            Some((SearchNodeData::Term(win), ..)) => {
+                if win {
+                    Proven::Loss
+                } else {
+                    Proven::Win
+                }
+            }
+            Some((SearchNodeData::Draw, ..)) => Proven::Draw,
+            _ => Proven::Unknown,
+        }
+    }
+
+    // the `k` most visited root moves with their principal variations
+    pub fn multi_pv(&self, k: usize) -> Vec<PvLine> {
+        let mut childs: Vec<&SearchNode> = match self.data {
+            Some((SearchNodeData::Mid { ref childs, .. }, ..)) => {
+                childs.iter().filter(|child| child.data.is_some()).collect()
+            }
+            _ => vec![],
+        };
+        childs.sort_by(|a, b| b.visits().partial_cmp(&a.visits()).unwrap());
+
+        childs
+            .into_iter()
+            .take(k)
+            .map(|child| {
+                let (.., a, b) = child.data.as_ref().unwrap();
+                let to_move = |(src, dst): (i32, i32)| Move::from_coords(src, dst);
+                let mut pv = vec![to_move(child.curr_move)];
+                pv.extend(child.principal_variation().into_iter().map(to_move));
+                PvLine {
+                    mv: to_move(child.curr_move),
+                    visits: *b,
+                    value: 1f32 - a / b,
+                    proven: child.proven(),
+                    pv,
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn analyse<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    iterations: usize,
+    k: usize,
+    seed: Option<u64>,
+) -> Vec<PvLine> {
+    let (root, _stats) = search_tree(board, rules, history, iterations, seed);
+    root.multi_pv(k)
+}
+
+// the `k` best moves of `turn` as an array of `{ move, visits, value, proven, pv }`;
+// null if `sparse` is not an 8x8 board
+#[wasm_bindgen]
+pub fn analyse_position(
+    turn: i32,
+    sparse: &[i32],
+    history: &[i32],
+    rules: &DrawRules,
+    k: usize,
+    seed: Option<u32>,
+) -> JsValue {
+    let board = match Geometry::default().from_sparse(sparse, turn) {
+        Some(board) => board,
+        None => return JsValue::NULL,
+    };
+    let history = GameHistory::from_sparse(history, Geometry::default(), turn, *rules);
+    let lines = analyse(&board, &Standard::default(), &history, MAX_NODE, k, seed.map(u64::from));
+    JsValue::from_serde(&lines).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(squares: &[(i32, i32)]) -> u64 {
+        squares.iter().fold(0, |mask, &sq| mask | sq.to_coord().to_piece())
+    }
+
+    // ordered by visits, one line per move, each starting with its move
+    fn check_lines(lines: &[PvLine]) {
+        for pair in lines.windows(2) {
+            assert!(pair[0].visits >= pair[1].visits);
+        }
+        for (i, line) in lines.iter().enumerate() {
+            assert_eq!(line.pv[0], line.mv);
+            assert!(lines[..i].iter().all(|other| other.mv != line.mv));
+        }
+    }
+
+    #[test]
+    fn lines_from_the_start() {
+        let rules = Standard::default();
+        let history = GameHistory::new(DrawRules::default());
+        let board = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        let lines = analyse(&board, &rules, &history, 400, 4, Some(2));
+        assert_eq!(lines.len(), 4);
+        check_lines(&lines);
+        assert!(lines.iter().all(|line| line.proven == Proven::Unknown && line.pv.len() > 1));
+    }
+
+    #[test]
+    fn lines_mark_proven_moves() {
+        let rules = Standard::default();
+        let history = GameHistory::new(DrawRules::default());
+        // black wins by (3, 1) -> (2, 1) and then on the next move, see the
+        // puzzle tests
+        let board = [
+            pieces(&[(3, 1), (0, 2), (0, 3)]),
+            pieces(&[(1, 2), (1, 5), (6, 7), (7, 7)]),
+        ];
+        let board = rules.apply_move(&board, (3, 1).to_coord(), (2, 1).to_coord());
+        let lines = analyse(&board, &rules, &history, 200, 64, Some(2));
+        check_lines(&lines);
+        let lost = lines.iter().find(|line| line.proven == Proven::Loss).unwrap();
+
+        // after which black has a move that wins at once
+        let (src, dst) = ((lost.mv.x0, lost.mv.y0).to_coord(), (lost.mv.x1, lost.mv.y1).to_coord());
+        let board = rules.apply_move(&board, src, dst);
+        let lines = analyse(&board, &rules, &history, 200, 64, Some(2));
+        check_lines(&lines);
+        assert_eq!(lines[0].proven, Proven::Win);
+    }
+}
@@ -0,0 +1,100 @@
+// native command line front end
+//
+//   loa analyse [options] [RECORD]
+//
+// searches every position of the game in RECORD (or standard input) and
+// prints the record annotated with mistakes and blunders, then the win rate
+// of the first mover after every move
+
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+use ai_frontend::{
+    analyse_game, parse_position, parse_record, start_position, GameAnalysisConfig, Geometry, Setup,
+    Variant,
+};
+
+const USAGE: &str = "usage: loa analyse [options] [RECORD]
+
+options:
+    --position TEXT   start position string, the standard start by default
+    --variant NAME    standard, black-hole or no-capture
+    --iterations N    search budget for every position
+    --mistake DROP    win rate drop that makes a mistake
+    --blunder DROP    win rate drop that makes a blunder
+    --seed N          seed for reproducible playouts";
+
+fn fail(message: &str) -> ! {
+    eprintln!("loa: {}", message);
+    process::exit(2);
+}
+
+fn value<T: std::str::FromStr>(args: &mut dyn Iterator<Item = String>, name: &str) -> T {
+    let arg = args.next().unwrap_or_else(|| fail(&format!("{} needs a value", name)));
+    arg.parse().unwrap_or_else(|_| fail(&format!("bad value `{}` for {}", arg, name)))
+}
+
+fn analyse(args: &mut dyn Iterator<Item = String>) {
+    let mut position = None;
+    let mut variant = "standard".to_string();
+    let mut config = GameAnalysisConfig::default();
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--position" => position = Some(value::<String>(args, &arg)),
+            "--variant" => variant = value(args, &arg),
+            "--iterations" => {
+                config.iterations = value(args, &arg);
+                if config.iterations == 0 {
+                    fail(&format!("{} needs at least 1", arg));
+                }
+            }
+            "--mistake" => config.mistake = value(args, &arg),
+            "--blunder" => config.blunder = value(args, &arg),
+            "--seed" => config.seed = Some(value(args, &arg)),
+            _ if arg.starts_with("--") => fail(&format!("unknown option `{}`\n{}", arg, USAGE)),
+            _ => path = Some(arg),
+        }
+    }
+
+    let (geo, board) = match position {
+        Some(position) => {
+            let (geo, board, _turn) = parse_position(&position).unwrap_or_else(|err| fail(&err));
+            (geo, board)
+        }
+        None => {
+            let geo = Geometry::default();
+            let sparse = start_position(Setup::Standard.name(), geo.size(), None);
+            (geo, geo.from_sparse(&sparse, 0).unwrap())
+        }
+    };
+    let rules = Variant::from_name(&variant, geo)
+        .unwrap_or_else(|| fail(&format!("unknown variant `{}`", variant)));
+
+    let mut text = String::new();
+    let read = match path {
+        Some(path) => fs::read_to_string(&path).map(|read| text = read),
+        None => io::stdin().read_to_string(&mut text).map(|_| ()),
+    };
+    if let Err(err) = read {
+        fail(&err.to_string());
+    }
+    let moves = parse_record(&rules, &text).unwrap_or_else(|err| fail(&err));
+
+    let analysis = analyse_game(&board, &rules, &moves, &config).unwrap_or_else(|err| fail(&err));
+    println!("{}", analysis.annotated_record());
+    println!();
+    for (ply, win_rate) in analysis.win_rates.iter().enumerate() {
+        println!("{:4} {:.3}", ply, win_rate);
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    match args.next().as_ref().map(String::as_str) {
+        Some("analyse") | Some("analyze") => analyse(&mut args),
+        _ => fail(USAGE),
+    }
+}
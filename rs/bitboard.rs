@@ -0,0 +1,230 @@
+use super::*;
+
+use query::{squares_of, Square};
+
+// the smallest rectangle holding a set of squares, corners included
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BoundingBox {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> i32 {
+        self.x1 - self.x0 + 1
+    }
+
+    pub fn height(&self) -> i32 {
+        self.y1 - self.y0 + 1
+    }
+}
+
+// the pieces of one side as a set of squares, bit x + 8 * y for (x, y)
+pub trait BitBoard {
+    // the squares next to any of them, diagonals included, and themselves
+    fn eight_conn_expand(self) -> Self;
+    // the lowest set square alone, 0 for none
+    fn lowest(self) -> Self;
+    // the squares connected to `seed` through pieces of `self`
+    fn group_from(self, seed: Self) -> Self;
+    // connected groups, in the order of their lowest square
+    fn groups(self) -> Vec<Self>
+    where
+        Self: Sized;
+    fn group_count(self) -> u32;
+    // true for a single group, false for none
+    fn is_one_group(self) -> bool;
+    fn bounding_box(self) -> Option<BoundingBox>;
+    // a lower bound on the moves to join all pieces into one group, if none
+    // of them is captured or taken off the board; smaller boards are the top
+    // left corner of the 8x8 one, where the bound holds as well
+    fn min_moves_to_connect(self) -> u32;
+}
+
+impl BitBoard for u64 {
+    fn eight_conn_expand(self) -> Self {
+        let line = self | self.overflowing_shl(8).0 | self.overflowing_shr(8).0;
+        line | (line & RM_LEFT).overflowing_shl(1).0 | (line & RM_RIGHT).overflowing_shr(1).0
+    }
+
+    fn lowest(self) -> Self {
+        self & self.wrapping_neg()
+    }
+
+    fn group_from(self, seed: Self) -> Self {
+        let mut conn = self & seed;
+        loop {
+            let new_conn = self & conn.eight_conn_expand();
+            if new_conn == conn {
+                return conn;
+            }
+            conn = new_conn;
+        }
+    }
+
+    fn groups(self) -> Vec<Self> {
+        let mut res = vec![];
+        let mut rest = self;
+        while rest != 0 {
+            let group = self.group_from(rest.lowest());
+            res.push(group);
+            rest &= !group;
+        }
+        res
+    }
+
+    fn group_count(self) -> u32 {
+        let mut count = 0;
+        let mut rest = self;
+        while rest != 0 {
+            rest &= !self.group_from(rest.lowest());
+            count += 1;
+        }
+        count
+    }
+
+    fn is_one_group(self) -> bool {
+        self != 0 && self.group_from(self.lowest()) == self
+    }
+
+    fn bounding_box(self) -> Option<BoundingBox> {
+        if self == 0 {
+            return None;
+        }
+        // every row folded onto the first one
+        let cols = (0..8).fold(0u8, |cols, y| cols | (self >> (8 * y)) as u8);
+        Some(BoundingBox {
+            x0: cols.trailing_zeros() as i32,
+            y0: self.trailing_zeros() as i32 / 8,
+            x1: 7 - cols.leading_zeros() as i32,
+            y1: 7 - self.leading_zeros() as i32 / 8,
+        })
+    }
+
+    fn min_moves_to_connect(self) -> u32 {
+        let count = self.count_ones();
+        let groups = self.group_count();
+        if groups <= 1 {
+            return 0;
+        }
+
+        // a piece landing next to at most 8 groups merges them, and it may
+        // leave a group of its own behind: at most 8 fewer per move
+        let by_groups = (groups - 1 + 7) / 8;
+
+        // one group of `count` pieces fits in a `count` x `count` square, and
+        // the pieces that stay put are all inside it
+        let side = count.min(8);
+        let cols = ((1u64 << side) - 1) * COL;
+        let rows = if side == 8 { !0u64 } else { (1u64 << (8 * side)) - 1 };
+        let mut most = 0;
+        for y in 0..9 - side {
+            for x in 0..9 - side {
+                let window = (cols << x) & (rows << (8 * y));
+                most = most.max((self & window).count_ones());
+            }
+        }
+        by_groups.max(count - most)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct GroupInfo {
+    size: u32,
+    bounds: BoundingBox,
+    squares: Vec<Square>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SideGroups {
+    groups: Vec<GroupInfo>,
+    min_moves_to_connect: u32,
+}
+
+// the connected groups of black and white on `sparse`, a `size` x `size`
+// board, largest first, as `[black, white]` of
+// `{ groups: [{ size, bounds, squares }], min_moves_to_connect }`; null if
+// the size is unsupported or `sparse` does not match it
+#[wasm_bindgen]
+pub fn group_analysis(sparse: &[i32], size: i32) -> JsValue {
+    let board = match Geometry::new(size).and_then(|geo| geo.from_sparse(sparse, 0)) {
+        Some(board) => board,
+        None => return JsValue::NULL,
+    };
+    let side = |pieces: u64| {
+        let mut groups: Vec<GroupInfo> = pieces
+            .groups()
+            .into_iter()
+            .map(|group| GroupInfo {
+                size: group.count_ones(),
+                bounds: group.bounding_box().unwrap(),
+                squares: squares_of(group),
+            })
+            .collect();
+        groups.sort_by(|a, b| b.size.cmp(&a.size));
+        SideGroups {
+            groups,
+            min_moves_to_connect: pieces.min_moves_to_connect(),
+        }
+    };
+    JsValue::from_serde(&[side(board[0]), side(board[1])]).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(squares: &[(i32, i32)]) -> u64 {
+        squares.iter().fold(0, |mask, &sq| mask | sq.to_coord().to_piece())
+    }
+
+    fn bbox(x0: i32, y0: i32, x1: i32, y1: i32) -> Option<BoundingBox> {
+        Some(BoundingBox { x0, y0, x1, y1 })
+    }
+
+    #[test]
+    fn single_group() {
+        let group = pieces(&[(2, 2), (3, 3), (3, 4)]);
+        assert_eq!(group.groups(), vec![group]);
+        assert_eq!(group.group_count(), 1);
+        assert!(group.is_one_group());
+        assert_eq!(group.bounding_box(), bbox(2, 2, 3, 4));
+        assert_eq!(group.min_moves_to_connect(), 0);
+    }
+
+    #[test]
+    fn disjoint_groups() {
+        let pair = pieces(&[(0, 0), (1, 0)]);
+        let (middle, corner) = (pieces(&[(5, 5)]), pieces(&[(7, 7)]));
+        let board = pair | middle | corner;
+        assert_eq!(board.groups(), vec![pair, middle, corner]);
+        assert_eq!(board.group_count(), 3);
+        assert!(!board.is_one_group());
+        assert_eq!(board.bounding_box(), bbox(0, 0, 7, 7));
+        // at most two pieces fit in any 4x4 square
+        assert_eq!(board.min_moves_to_connect(), 2);
+    }
+
+    #[test]
+    fn diagonal_adjacency() {
+        let diagonal = pieces(&[(0, 0), (1, 1), (2, 0)]);
+        assert_eq!(diagonal.group_count(), 1);
+        assert_eq!(diagonal.min_moves_to_connect(), 0);
+        // the end of one row does not touch the start of the next
+        let wrapped = pieces(&[(7, 0), (0, 1)]);
+        assert_eq!(wrapped.group_count(), 2);
+        assert_eq!(wrapped.bounding_box(), bbox(0, 0, 7, 1));
+        assert_eq!(wrapped.min_moves_to_connect(), 1);
+    }
+
+    #[test]
+    fn empty_board() {
+        assert!(0u64.groups().is_empty());
+        assert_eq!(0u64.group_count(), 0);
+        assert!(!0u64.is_one_group());
+        assert_eq!(0u64.bounding_box(), None);
+        assert_eq!(0u64.min_moves_to_connect(), 0);
+    }
+}
@@ -0,0 +1,357 @@
+use super::*;
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use notation::record_name;
+use query::is_legal_move_of;
+
+// file layout, all little endian:
+//   "LOAB", version: u16, count: u32,
+//   count * { hash: u64, src: u8, dst: u8, weight: u16 }, sorted by hash
+const BOOK_MAGIC: &[u8; 4] = b"LOAB";
+const BOOK_VERSION: u16 = 1;
+const BOOK_HEADER_LEN: usize = 10;
+const BOOK_ENTRY_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookEntry {
+    pub hash: u64,
+    pub src: u8,
+    pub dst: u8,
+    pub weight: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookError {
+    BadMagic,
+    BadVersion(u16),
+    Truncated,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    entries: Vec<BookEntry>,
+}
+
+// books are keyed by the board relative to the side to move, and only hold
+// positions on the standard board
+fn book_hash(board: &[u64; 2]) -> u64 {
+    board.position_hash(0)
+}
+
+pub(crate) fn read_u16(bytes: &[u8]) -> u16 {
+    bytes[0] as u16 | (bytes[1] as u16) << 8
+}
+
+pub(crate) fn read_u32(bytes: &[u8]) -> u32 {
+    (0..4).fold(0u32, |val, i| val | (bytes[i] as u32) << (8 * i))
+}
+
+pub(crate) fn read_u64(bytes: &[u8]) -> u64 {
+    (0..8).fold(0u64, |val, i| val | (bytes[i] as u64) << (8 * i))
+}
+
+pub(crate) fn write_le(out: &mut Vec<u8>, val: u64, len: usize) {
+    for i in 0..len {
+        out.push((val >> (8 * i)) as u8);
+    }
+}
+
+impl OpeningBook {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if bytes.len() < BOOK_HEADER_LEN {
+            return Err(BookError::Truncated);
+        }
+        if &bytes[..4] != BOOK_MAGIC {
+            return Err(BookError::BadMagic);
+        }
+        let version = read_u16(&bytes[4..]);
+        if version != BOOK_VERSION {
+            return Err(BookError::BadVersion(version));
+        }
+        let count = read_u32(&bytes[6..]) as usize;
+        let body = &bytes[BOOK_HEADER_LEN..];
+        // a count this large cannot be backed by the bytes anyway, and it
+        // would overflow on 32 bit targets
+        match count.checked_mul(BOOK_ENTRY_LEN) {
+            Some(len) if len <= body.len() => {}
+            _ => return Err(BookError::Truncated),
+        }
+
+        let mut entries: Vec<BookEntry> = body
+            .chunks(BOOK_ENTRY_LEN)
+            .take(count)
+            .map(|entry| BookEntry {
+                hash: read_u64(entry),
+                src: entry[8],
+                dst: entry[9],
+                weight: read_u16(&entry[10..]),
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.hash);
+        Ok(OpeningBook { entries })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(BOOK_HEADER_LEN + self.entries.len() * BOOK_ENTRY_LEN);
+        out.extend_from_slice(BOOK_MAGIC);
+        write_le(&mut out, BOOK_VERSION as u64, 2);
+        write_le(&mut out, self.entries.len() as u64, 4);
+        for entry in self.entries.iter() {
+            write_le(&mut out, entry.hash, 8);
+            out.push(entry.src);
+            out.push(entry.dst);
+            write_le(&mut out, entry.weight as u64, 2);
+        }
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // book moves for the side to move on `board`
+    pub fn moves(&self, board: &[u64; 2]) -> &[BookEntry] {
+        let hash = book_hash(board);
+        // never `Equal`, so these find the bounds of the run of `hash`
+        let bound = |upper: bool| {
+            let res = self.entries.binary_search_by(|entry| {
+                if entry.hash < hash || (upper && entry.hash == hash) {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            });
+            res.unwrap_or_else(|i| i)
+        };
+        &self.entries[bound(false)..bound(true)]
+    }
+
+    // a legal book move, chosen at random by weight
+    pub fn pick(&self, board: &[u64; 2], rng: &mut RngProvider) -> Option<(i32, i32)> {
+        let moves: Vec<&BookEntry> = self
+            .moves(board)
+            .iter()
+            .filter(|entry| {
+                is_legal_move_of(board, &Standard::default(), entry.src as i32, entry.dst as i32)
+            })
+            .collect();
+        let total: u32 = moves.iter().map(|entry| entry.weight as u32).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut rn = rng.below(total);
+        for entry in moves {
+            if rn < entry.weight as u32 {
+                return Some((entry.src as i32, entry.dst as i32));
+            }
+            rn -= entry.weight as u32;
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    // only the first `max_ply` moves of a game go into the book
+    pub max_ply: usize,
+    weights: HashMap<(u64, u8, u8), f64>,
+}
+
+impl BookBuilder {
+    pub fn new(max_ply: usize) -> Self {
+        BookBuilder {
+            max_ply,
+            weights: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, board: &[u64; 2], src: i32, dst: i32, weight: f64) {
+        *self
+            .weights
+            .entry((book_hash(board), src as u8, dst as u8))
+            .or_insert(0f64) += weight;
+    }
+
+    // `board` is the start position relative to the first mover
+    pub fn add_game(&mut self, board: &[u64; 2], moves: &[(i32, i32)]) -> Result<(), String> {
+        let mut board = *board;
+        for &(src, dst) in moves.iter().take(self.max_ply) {
+            if !is_legal_move_of(&board, &Standard::default(), src, dst) {
+                let name = record_name(&board, &Standard::default(), &[(src, dst)]);
+                return Err(format!("illegal move in `{}`", name));
+            }
+            self.add(&board, src, dst, 1f64);
+            board = board.apply_move(src, dst);
+        }
+        Ok(())
+    }
+
+    // searches `board` and adds its root moves weighted by visits, then does
+    // the same for the positions after the `width` best moves until `depth`
+    pub fn add_search(
+        &mut self,
+        board: &[u64; 2],
+        depth: usize,
+        width: usize,
+        iterations: usize,
+        seed: Option<u64>,
+    ) {
+        if depth == 0 || board.is_win_state().is_some() {
+            return;
+        }
+        let history = GameHistory::new(DrawRules::default());
+        let (root, _stats) = search_tree(board, &Standard::default(), &history, iterations, seed);
+        let lines = root.multi_pv(width);
+        let visits: f32 = lines.iter().map(|line| line.visits).sum();
+        for line in lines {
+            let src = (line.mv.x0, line.mv.y0).to_coord();
+            let dst = (line.mv.x1, line.mv.y1).to_coord();
+            self.add(board, src, dst, (line.visits / visits) as f64);
+            let next = board.apply_move(src, dst);
+            self.add_search(&next, depth - 1, width, iterations, seed);
+        }
+    }
+
+    // weights are scaled per position so the best move gets 65535, moves
+    // below 1/65535 of it are dropped
+    pub fn build(&self) -> OpeningBook {
+        let mut best: HashMap<u64, f64> = HashMap::new();
+        for (&(hash, ..), &weight) in self.weights.iter() {
+            let max = best.entry(hash).or_insert(0f64);
+            *max = max.max(weight);
+        }
+        let mut entries: Vec<BookEntry> = self
+            .weights
+            .iter()
+            .map(|(&(hash, src, dst), &weight)| BookEntry {
+                hash,
+                src,
+                dst,
+                weight: (weight / best[&hash] * 65535f64).round() as u16,
+            })
+            .filter(|entry| entry.weight != 0)
+            .collect();
+        entries.sort_by_key(|entry| (entry.hash, entry.src, entry.dst));
+        OpeningBook { entries }
+    }
+}
+
+thread_local! {
+    static BOOK: RefCell<Option<OpeningBook>> = RefCell::new(None);
+}
+
+// installs the book used by the exported bot functions, returns whether
+// `bytes` was a valid book
+#[wasm_bindgen]
+pub fn load_opening_book(bytes: &[u8]) -> bool {
+    match OpeningBook::from_bytes(bytes) {
+        Ok(book) => {
+            BOOK.with(|cell| *cell.borrow_mut() = Some(book));
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[wasm_bindgen]
+pub fn unload_opening_book() {
+    BOOK.with(|cell| *cell.borrow_mut() = None);
+}
+
+pub fn book_move(board: &[u64; 2], rng: &mut RngProvider) -> Option<(i32, i32)> {
+    BOOK.with(|cell| cell.borrow().as_ref().and_then(|book| book.pick(board, rng)))
+}
+
+// `book_move`, but None unless `rules` are the standard game on the
+// standard board
+pub fn variant_book_move(
+    board: &[u64; 2],
+    rules: &Variant,
+    rng: &mut RngProvider,
+) -> Option<(i32, i32)> {
+    if *rules != Variant::default() {
+        return None;
+    }
+    book_move(board, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start() -> [u64; 2] {
+        setup::Setup::Standard.board(Geometry::default(), &mut RngProvider::from_seed(0))
+    }
+
+    fn book() -> OpeningBook {
+        let start = start();
+        let mut builder = BookBuilder::new(4);
+        let first = ((1, 0).to_coord(), (1, 2).to_coord());
+        let second = ((2, 0).to_coord(), (2, 2).to_coord());
+        builder.add_game(&start, &[first, ((0, 1).to_coord(), (2, 1).to_coord())]).unwrap();
+        builder.add_game(&start, &[first]).unwrap();
+        builder.add_game(&start, &[second]).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn round_trip() {
+        let book = book();
+        let bytes = book.to_bytes();
+        assert_eq!(&bytes[..4], BOOK_MAGIC);
+        let read = OpeningBook::from_bytes(&bytes).unwrap();
+        assert_eq!(read.entries, book.entries);
+        assert_eq!(read.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn weights_follow_the_games() {
+        let book = book();
+        let weights: Vec<u16> = book.moves(&start()).iter().map(|entry| entry.weight).collect();
+        assert_eq!(book.len(), 3);
+        assert_eq!(weights.iter().max(), Some(&65535));
+        assert!(weights.contains(&32768));
+    }
+
+    fn in_book(src: i32, dst: i32) -> bool {
+        let entries = book().moves(&start()).to_vec();
+        entries.iter().any(|entry| (entry.src, entry.dst) == (src as u8, dst as u8))
+    }
+
+    #[test]
+    fn books_are_for_the_standard_game() {
+        assert!(load_opening_book(&book().to_bytes()));
+        let mut rng = RngProvider::from_seed(1);
+        let black_hole = Variant::from_name("black-hole", Geometry::default()).unwrap();
+        assert!(variant_book_move(&start(), &black_hole, &mut rng).is_none());
+        let (src, dst) = variant_book_move(&start(), &Variant::default(), &mut rng).unwrap();
+        assert!(in_book(src, dst));
+
+        // the exported bots play from the book before searching
+        let sparse = Geometry::default().to_sparse(&start(), 0);
+        let m = my_plain_solution_level(0, &sparse, "beginner", Some(1)).unwrap();
+        let (src, dst) = ((m.x0, m.y0).to_coord(), (m.x1, m.y1).to_coord());
+        assert!(in_book(src, dst));
+        unload_opening_book();
+    }
+
+    #[test]
+    fn bad_books() {
+        let bytes = book().to_bytes();
+        let mut magic = bytes.clone();
+        magic[3] = b'X';
+        assert_eq!(OpeningBook::from_bytes(&magic).unwrap_err(), BookError::BadMagic);
+        let short = &bytes[..bytes.len() - 1];
+        assert_eq!(OpeningBook::from_bytes(short).unwrap_err(), BookError::Truncated);
+        let mut huge = bytes.clone();
+        huge[6..10].copy_from_slice(&[0xff; 4]);
+        assert_eq!(OpeningBook::from_bytes(&huge).unwrap_err(), BookError::Truncated);
+    }
+}
@@ -0,0 +1,215 @@
+use super::*;
+
+use notation::move_name;
+
+// edges of the DOT graph are this much wider for a move with all of the
+// root visits than for one with none
+const DOT_MAX_PENWIDTH: f32 = 8f32;
+
+// a copy of the visited part of a search tree, cut to a depth and to moves
+// with enough visits, with the figures spelled out
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeSnapshot {
+    // in notation, None at the root
+    #[serde(rename = "move")]
+    pub mv: Option<String>,
+    pub visits: f32,
+    // playouts won and mean value for the side that played `move`; at the
+    // root for the side to move, as in `SearchResult`
+    pub wins: f32,
+    pub value: f32,
+    pub proven: Proven,
+    pub children: Vec<TreeSnapshot>,
+}
+
+impl SearchNode {
+    // `board` is the position at this node, relative to the side to move;
+    // children below `min_visits` visits or deeper than `max_depth` plies are
+    // left out
+    pub fn snapshot(
+        &self,
+        board: &[u64; 2],
+        geo: Geometry,
+        max_depth: u32,
+        min_visits: f32,
+    ) -> TreeSnapshot {
+        let (a, b) = self.data.as_ref().map_or((0f32, 0f32), |(_, a, b)| (*a, *b));
+        let mut snapshot = TreeSnapshot {
+            mv: None,
+            visits: b,
+            wins: a,
+            value: if b > 0f32 { a / b } else { 0f32 },
+            proven: self.proven(),
+            children: vec![],
+        };
+        snapshot.children = self.snapshot_children(board, geo, max_depth, min_visits);
+        snapshot
+    }
+
+    fn snapshot_children(
+        &self,
+        board: &[u64; 2],
+        geo: Geometry,
+        depth: u32,
+        min_visits: f32,
+    ) -> Vec<TreeSnapshot> {
+        let childs = match self.data {
+            Some((SearchNodeData::Mid { ref childs, .. }, ..)) if depth > 0 => childs,
+            _ => return vec![],
+        };
+        let mut children: Vec<TreeSnapshot> = childs
+            .iter()
+            .filter(|child| child.data.is_some() && child.visits() >= min_visits)
+            .map(|child| {
+                let (data, a, b) = child.data.as_ref().unwrap();
+                let (src, dst) = child.curr_move;
+                // only inner nodes keep their board
+                let children = match data {
+                    SearchNodeData::Mid { board, .. } => {
+                        child.snapshot_children(board, geo, depth - 1, min_visits)
+                    }
+                    _ => vec![],
+                };
+                TreeSnapshot {
+                    mv: Some(move_name(board, geo, src, dst)),
+                    visits: *b,
+                    wins: b - a,
+                    value: 1f32 - a / b,
+                    proven: child.proven(),
+                    children,
+                }
+            })
+            .collect();
+        children.sort_by(|x, y| y.visits.partial_cmp(&x.visits).unwrap());
+        children
+    }
+}
+
+impl TreeSnapshot {
+    // a Graphviz digraph of the snapshot, edges as wide as their share of the
+    // root visits, proven wins and losses for the mover in green and red
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec![
+            "digraph search {".to_string(),
+            "    node [shape=box, fontname=\"monospace\"];".to_string(),
+        ];
+        self.dot_lines(&mut lines, &mut 0, self.visits.max(1f32));
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    // adds this node as `n<next_id>` and then its subtree, returns its id
+    fn dot_lines(&self, lines: &mut Vec<String>, next_id: &mut u32, total: f32) -> u32 {
+        let id = *next_id;
+        *next_id += 1;
+
+        let color = match self.proven {
+            Proven::Win => ", color=green",
+            Proven::Loss => ", color=red",
+            _ => "",
+        };
+        lines.push(format!(
+            "    n{} [label=\"{}\\n{} visits\\nvalue {:.3}\"{}];",
+            id,
+            self.mv.as_ref().map_or("root", String::as_str),
+            self.visits,
+            self.value,
+            color
+        ));
+
+        for child in self.children.iter() {
+            let child_id = child.dot_lines(lines, next_id, total);
+            let width = 1f32 + (DOT_MAX_PENWIDTH - 1f32) * child.visits / total;
+            lines.push(format!("    n{} -> n{} [penwidth={:.2}];", id, child_id, width));
+        }
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn searched() -> (SearchNode, [u64; 2]) {
+        let rules = Standard::default();
+        let board = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        let history = GameHistory::new(DrawRules::default());
+        (search_tree(&board, &rules, &history, 300, Some(1)).0, board)
+    }
+
+    // visited nodes up to `depth` plies below `node`
+    fn tree_size(node: &SearchNode, depth: u32) -> usize {
+        match node.data {
+            Some((SearchNodeData::Mid { ref childs, .. }, ..)) if depth > 0 => {
+                let below: usize = childs
+                    .iter()
+                    .filter(|child| child.data.is_some())
+                    .map(|child| tree_size(child, depth - 1))
+                    .sum();
+                1 + below
+            }
+            Some(_) => 1,
+            None => 0,
+        }
+    }
+
+    fn size(snapshot: &TreeSnapshot) -> usize {
+        1 + snapshot.children.iter().map(size).sum::<usize>()
+    }
+
+    fn depth(snapshot: &TreeSnapshot) -> u32 {
+        snapshot.children.iter().map(|child| 1 + depth(child)).max().unwrap_or(0)
+    }
+
+    #[test]
+    fn snapshots_keep_to_the_limits() {
+        let (root, board) = searched();
+        assert!(tree_size(&root, 100) > tree_size(&root, 1));
+        let snapshot = root.snapshot(&board, Geometry::default(), 1, 0f32);
+        assert_eq!(size(&snapshot), tree_size(&root, 1));
+        assert_eq!(depth(&snapshot), 1);
+        assert_eq!(snapshot.visits, root.visits());
+        assert!(snapshot.mv.is_none());
+        let visits: Vec<f32> = snapshot.children.iter().map(|child| child.visits).collect();
+        assert!(visits.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        // visits come in batches of playouts, this keeps part of the root moves
+        let busy = root.snapshot(&board, Geometry::default(), 100, 130f32);
+        fn check(snapshot: &TreeSnapshot) {
+            for child in snapshot.children.iter() {
+                assert!(child.visits >= 130f32 && child.mv.is_some());
+                check(child);
+            }
+        }
+        check(&busy);
+        assert!(!busy.children.is_empty());
+        assert!(busy.children.len() < snapshot.children.len());
+    }
+
+    #[test]
+    fn dot_is_well_formed() {
+        let (root, board) = searched();
+        let snapshot = root.snapshot(&board, Geometry::default(), 2, 0f32);
+        let dot = snapshot.to_dot();
+        let lines: Vec<&str> = dot.lines().collect();
+        assert_eq!(lines[0], "digraph search {");
+        assert_eq!(lines[lines.len() - 1], "}");
+
+        let body = &lines[1..lines.len() - 1];
+        assert!(body.iter().all(|line| line.ends_with("];") && line.matches('"').count() % 2 == 0));
+        let nodes: Vec<&str> = body
+            .iter()
+            .filter(|line| line.contains("[label="))
+            .map(|line| line.trim().split(' ').next().unwrap())
+            .collect();
+        let edges: Vec<&&str> = body.iter().filter(|line| line.contains(" -> ")).collect();
+        assert_eq!(nodes.len(), size(&snapshot));
+        assert_eq!(edges.len(), nodes.len() - 1);
+        for edge in edges {
+            let mut ends = edge.trim().split(" -> ");
+            let src = ends.next().unwrap();
+            let dst = ends.next().unwrap().split(' ').next().unwrap();
+            assert!(nodes.contains(&src) && nodes.contains(&dst), "{}", edge);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use super::*;
+
+// smaller boards use the top left corner of the bitboard with the same
+// stride, so a square is still bit x + 8 * y and `Coord` works unchanged;
+// only the bounds of a move depend on the size
+pub const MIN_SIZE: i32 = 4;
+pub const MAX_SIZE: i32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry {
+    size: i32,
+    // the squares on the board
+    area: u64,
+}
+
+impl Geometry {
+    pub fn new(size: i32) -> Option<Geometry> {
+        if size < MIN_SIZE || size > MAX_SIZE {
+            return None;
+        }
+        let row = ROW >> (MAX_SIZE - size);
+        let area = (0..size).fold(0u64, |area, y| area | row << (8 * y));
+        Some(Geometry { size, area })
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn area(&self) -> u64 {
+        self.area
+    }
+
+    // the length of a sparse board
+    pub fn squares(&self) -> usize {
+        (self.size * self.size) as usize
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.size && y < self.size
+    }
+
+    // `sparse` lists the squares row by row, -1 for empty and 0 / 1 for the
+    // pieces of either side; None unless it has exactly `squares()` entries
+    pub fn from_sparse(&self, sparse: &[i32], turn: i32) -> Option<[u64; 2]> {
+        if sparse.len() != self.squares() {
+            return None;
+        }
+        let mut board = [0u64; 2];
+        for (i, &id) in sparse.iter().enumerate() {
+            let (x, y) = (i as i32 % self.size, i as i32 / self.size);
+            match id {
+                -1 => {}
+                0 | 1 => board[id as usize] |= (x, y).to_coord().to_piece(),
+                _ => return None,
+            }
+        }
+        if turn == 0 {
+            Some(board)
+        } else {
+            Some([board[1], board[0]])
+        }
+    }
+
+    // the inverse of `from_sparse`
+    pub fn to_sparse(&self, board: &[u64; 2], turn: i32) -> Vec<i32> {
+        let mut sparse = vec![-1; self.squares()];
+        for (i, square) in sparse.iter_mut().enumerate() {
+            let piece = (i as i32 % self.size, i as i32 / self.size).to_coord().to_piece();
+            for id in 0..2 {
+                if (board[id] & piece) != 0 {
+                    *square = id as i32 ^ turn;
+                }
+            }
+        }
+        sparse
+    }
+
+    // whether `board` fits on this geometry
+    pub fn holds(&self, board: &[u64; 2]) -> bool {
+        ((board[0] | board[1]) & !self.area) == 0
+    }
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Geometry::new(MAX_SIZE).unwrap()
+    }
+}
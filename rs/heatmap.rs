@@ -0,0 +1,181 @@
+use super::*;
+
+// squares of the largest board, indexed by x + 8 * y like the bitboards
+const HEATMAP_SQUARES: usize = 64;
+
+// where the search at the root spent its visits, per square a move starts
+// from and per square it lands on
+#[derive(Debug, Clone, Serialize)]
+pub struct Heatmap {
+    pub source_visits: Vec<f32>,
+    // mean value of the moves from / to the square for the side to move,
+    // weighted by visits; 0 where there are none
+    pub source_values: Vec<f32>,
+    pub dest_visits: Vec<f32>,
+    pub dest_values: Vec<f32>,
+    // the most visited moves, most first
+    pub top: Vec<MoveArrow>,
+}
+
+// a root move laid out for drawing an arrow from (x0, y0) to (x1, y1)
+#[derive(Debug, Clone, Serialize)]
+pub struct MoveArrow {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+    pub visits: f32,
+    // for the side to move
+    pub value: f32,
+    // of the visits of all root moves
+    pub share: f32,
+}
+
+impl SearchNode {
+    // the heatmap of the visited children, with the `top` most visited moves
+    pub fn heatmap(&self, top: usize) -> Heatmap {
+        let mut heatmap = Heatmap {
+            source_visits: vec![0f32; HEATMAP_SQUARES],
+            source_values: vec![0f32; HEATMAP_SQUARES],
+            dest_visits: vec![0f32; HEATMAP_SQUARES],
+            dest_values: vec![0f32; HEATMAP_SQUARES],
+            top: vec![],
+        };
+        let childs = match self.data {
+            Some((SearchNodeData::Mid { ref childs, .. }, ..)) => childs,
+            _ => return heatmap,
+        };
+
+        // (move, visits, value) of every visited child
+        let mut stats: Vec<((i32, i32), f32, f32)> = childs
+            .iter()
+            .filter_map(|child| {
+                let (.., a, b) = child.data.as_ref()?;
+                Some((child.curr_move, *b, 1f32 - a / b))
+            })
+            .collect();
+
+        // values summed times visits first, divided below
+        for &((src, dst), visits, value) in stats.iter() {
+            heatmap.source_visits[src as usize] += visits;
+            heatmap.source_values[src as usize] += visits * value;
+            heatmap.dest_visits[dst as usize] += visits;
+            heatmap.dest_values[dst as usize] += visits * value;
+        }
+        for (values, visits) in [
+            (&mut heatmap.source_values, &heatmap.source_visits),
+            (&mut heatmap.dest_values, &heatmap.dest_visits),
+        ]
+        .iter_mut()
+        {
+            for (value, &visits) in values.iter_mut().zip(visits.iter()) {
+                if visits > 0f32 {
+                    *value /= visits;
+                }
+            }
+        }
+
+        let total: f32 = stats.iter().map(|&(_, visits, _)| visits).sum();
+        stats.sort_by(|x, y| y.1.partial_cmp(&x.1).unwrap());
+        heatmap.top = stats
+            .into_iter()
+            .take(top)
+            .map(|((src, dst), visits, value)| {
+                let (x0, y0) = src.to_coord_2d();
+                let (x1, y1) = dst.to_coord_2d();
+                MoveArrow {
+                    x0,
+                    y0,
+                    x1,
+                    y1,
+                    visits,
+                    value,
+                    share: visits / total,
+                }
+            })
+            .collect();
+        heatmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn child(src: (i32, i32), dst: (i32, i32), visits: f32, value: f32) -> SearchNode {
+        SearchNode {
+            curr_move: (src.to_coord(), dst.to_coord()),
+            data: Some((SearchNodeData::Draw, (1f32 - value) * visits, visits)),
+        }
+    }
+
+    fn close(x: f32, y: f32) -> bool {
+        (x - y).abs() < 1e-4f32
+    }
+
+    #[test]
+    fn heatmap_of_the_root_moves() {
+        let childs = vec![
+            child((0, 0), (1, 0), 10f32, 0.6f32),
+            child((0, 0), (2, 0), 30f32, 0.2f32),
+            child((1, 1), (2, 0), 20f32, 0.5f32),
+            // never visited, left out
+            SearchNode {
+                curr_move: ((1, 1).to_coord(), (3, 3).to_coord()),
+                data: None,
+            },
+        ];
+        let root = SearchNode {
+            curr_move: EMPTY_MOVE,
+            data: Some((
+                SearchNodeData::Mid {
+                    curr: 0,
+                    full: true,
+                    board: [0, 0],
+                    childs,
+                },
+                30f32,
+                60f32,
+            )),
+        };
+        let heatmap = root.heatmap(2);
+        let at = |sq: (i32, i32)| sq.to_coord() as usize;
+
+        assert_eq!(heatmap.source_visits[at((0, 0))], 40f32);
+        assert_eq!(heatmap.source_visits[at((1, 1))], 20f32);
+        assert_eq!(heatmap.dest_visits[at((1, 0))], 10f32);
+        assert_eq!(heatmap.dest_visits[at((2, 0))], 50f32);
+        assert_eq!(heatmap.dest_visits[at((3, 3))], 0f32);
+        // both maps account for every visit of the root moves
+        assert_eq!(heatmap.source_visits.iter().sum::<f32>(), 60f32);
+        assert_eq!(heatmap.dest_visits.iter().sum::<f32>(), 60f32);
+
+        // weighted by visits
+        assert!(close(heatmap.source_values[at((0, 0))], 0.3f32));
+        assert!(close(heatmap.source_values[at((1, 1))], 0.5f32));
+        assert!(close(heatmap.dest_values[at((2, 0))], 0.32f32));
+        assert_eq!(heatmap.dest_values[at((3, 3))], 0f32);
+
+        // most visited first, shares of all root visits
+        let top: Vec<(i32, i32, f32)> =
+            heatmap.top.iter().map(|arrow| (arrow.x0, arrow.y0, arrow.visits)).collect();
+        assert_eq!(top, vec![(0, 0, 30f32), (1, 1, 20f32)]);
+        assert!(close(heatmap.top[0].share, 0.5f32));
+        assert!(close(heatmap.top[1].value, 0.5f32));
+    }
+
+    #[test]
+    fn heatmap_of_a_search() {
+        let rules = Standard::default();
+        let board = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        let history = GameHistory::new(DrawRules::default());
+        let (root, _stats) = search_tree(&board, &rules, &history, 200, Some(2));
+        let heatmap = root.heatmap(100);
+        let visits: f32 = heatmap.top.iter().map(|arrow| arrow.visits).sum();
+        assert!(close(heatmap.source_visits.iter().sum(), visits));
+        assert!(close(heatmap.dest_visits.iter().sum(), visits));
+        assert!(close(heatmap.top.iter().map(|arrow| arrow.share).sum(), 1f32));
+        assert!(heatmap.top.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+        assert!(heatmap.top.len() > 1);
+    }
+}
@@ -0,0 +1,202 @@
+use super::*;
+
+use policy::FinalPolicy;
+use puzzle::forced_win;
+
+// a short search, a hint should come back at once
+const HINT_ITERATIONS: usize = 1024;
+
+// what a move does to the board, in terms a beginner can follow
+#[derive(Debug, Clone, Serialize)]
+pub struct MoveExplanation {
+    pub capture: bool,
+    // connected groups of the mover's pieces, and of the opponent's
+    pub groups_before: u32,
+    pub groups_after: u32,
+    pub opponent_groups_before: u32,
+    pub opponent_groups_after: u32,
+    // mean distance of the mover's pieces from their centre of mass; lower
+    // is closer to connecting
+    pub spread_before: f32,
+    pub spread_after: f32,
+    // the move connects all of the mover's pieces
+    pub wins: bool,
+    // the game goes on and the mover could win with its next move
+    pub threat: bool,
+    // the opponent could have won with its next move, and no longer can
+    pub blocks_threat: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Hint {
+    #[serde(rename = "move")]
+    pub mv: Move,
+    // for the side to move
+    pub value: f32,
+    pub explanation: MoveExplanation,
+}
+
+// mean distance of `pieces` from their centre of mass
+fn spread(pieces: u64) -> f32 {
+    let squares: Vec<(f32, f32)> = (0..64)
+        .filter(|pos: &i32| (pieces & pos.to_piece()) != 0)
+        .map(|pos| {
+            let (x, y) = pos.to_coord_2d();
+            (x as f32, y as f32)
+        })
+        .collect();
+    if squares.is_empty() {
+        return 0f32;
+    }
+    let n = squares.len() as f32;
+    let cx = squares.iter().map(|sq| sq.0).sum::<f32>() / n;
+    let cy = squares.iter().map(|sq| sq.1).sum::<f32>() / n;
+    squares.iter().map(|(x, y)| ((x - cx).powi(2) + (y - cy).powi(2)).sqrt()).sum::<f32>() / n
+}
+
+// the explanation of `src` -> `dst` on `board`, relative to the mover
+pub fn explain_move<R: Rules>(board: &[u64; 2], rules: &R, src: i32, dst: i32) -> MoveExplanation {
+    let next = rules.apply_move(board, src, dst);
+    // `next` is relative to the opponent
+    let (own, other) = (next[1], next[0]);
+    let wins = rules.is_win_state(&next) == Some(false);
+    MoveExplanation {
+        capture: (board[1] & dst.to_piece()) != 0,
+        groups_before: board[0].group_count(),
+        groups_after: own.group_count(),
+        opponent_groups_before: board[1].group_count(),
+        opponent_groups_after: other.group_count(),
+        spread_before: spread(board[0]),
+        spread_after: spread(own),
+        wins,
+        // as if the opponent passed
+        threat: !wins && forced_win(&[own, other], rules, 1),
+        // winning ends the game before the opponent moves
+        blocks_threat: forced_win(&[board[1], board[0]], rules, 1)
+            && (wins || !forced_win(&next, rules, 1)),
+    }
+}
+
+// the move a short search recommends on `board`, None if there is none
+pub fn hint_of<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    iterations: usize,
+    seed: Option<u64>,
+) -> Option<Hint> {
+    if rules.is_win_state(board).is_some() || rules.moves(board).is_empty() {
+        return None;
+    }
+    let (root, _stats) = search_tree(board, rules, history, iterations, seed);
+    let mut rng = RngProvider::with_seed(seed);
+    let (src, dst) = root.final_move(FinalPolicy::default(), &mut rng)?;
+    let value = match root.data {
+        Some((SearchNodeData::Mid { ref childs, .. }, ..)) => childs
+            .iter()
+            .find(|child| child.curr_move == (src, dst))
+            .and_then(|child| child.data.as_ref())
+            .map_or(0.5f32, |(.., a, b)| 1f32 - a / b),
+        _ => 0.5f32,
+    };
+    Some(Hint {
+        mv: Move::from_coords(src, dst),
+        value,
+        explanation: explain_move(board, rules, src, dst),
+    })
+}
+
+// a recommended move for `turn` with why it is good, as
+// `{ move, value, explanation }`; null if there is no move to recommend
+#[wasm_bindgen]
+pub fn hint(turn: i32, sparse: &[i32], seed: Option<u32>) -> JsValue {
+    let rules = Standard::default();
+    let history = GameHistory::new(DrawRules::default());
+    let hint = rules
+        .geometry()
+        .from_sparse(sparse, turn)
+        .and_then(|board| hint_of(&board, &rules, &history, HINT_ITERATIONS, seed.map(u64::from)));
+    match hint {
+        Some(hint) => JsValue::from_serde(&hint).unwrap(),
+        None => JsValue::NULL,
+    }
+}
+
+// the explanation of `m` for `turn`, null if it is not legal
+#[wasm_bindgen]
+pub fn explain(turn: i32, sparse: &[i32], m: &Move) -> JsValue {
+    let rules = Standard::default();
+    let geo = rules.geometry();
+    match geo.from_sparse(sparse, turn) {
+        Some(board) if is_legal_move(turn, sparse, m) => {
+            let (src, dst) = ((m.x0, m.y0).to_coord(), (m.x1, m.y1).to_coord());
+            JsValue::from_serde(&explain_move(&board, &rules, src, dst)).unwrap()
+        }
+        _ => JsValue::NULL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(squares: &[(i32, i32)]) -> u64 {
+        squares.iter().fold(0, |mask, &sq| mask | sq.to_coord().to_piece())
+    }
+
+    fn explain(
+        own: &[(i32, i32)],
+        other: &[(i32, i32)],
+        src: (i32, i32),
+        dst: (i32, i32),
+    ) -> MoveExplanation {
+        let board = [pieces(own), pieces(other)];
+        explain_move(&board, &Standard::default(), src.to_coord(), dst.to_coord())
+    }
+
+    #[test]
+    fn winning_capture() {
+        // the far piece lands on the one beside the corner and joins up
+        let (own, other) = (&[(0, 0), (1, 1), (4, 0)], &[(1, 0), (7, 7), (7, 2)]);
+        let explanation = explain(own, other, (4, 0), (1, 0));
+        assert!(explanation.wins && explanation.capture);
+        assert!(!explanation.threat && !explanation.blocks_threat);
+        assert_eq!((explanation.groups_before, explanation.groups_after), (2, 1));
+        let opponent = (explanation.opponent_groups_before, explanation.opponent_groups_after);
+        assert_eq!(opponent, (3, 2));
+        assert!(explanation.spread_after < explanation.spread_before);
+    }
+
+    #[test]
+    fn threat() {
+        // one step short, the next move along the row connects
+        let explanation = explain(&[(0, 0), (1, 0), (3, 2)], &[(7, 7), (7, 2)], (3, 2), (3, 1));
+        assert!(explanation.threat);
+        assert!(!explanation.wins && !explanation.capture && !explanation.blocks_threat);
+        assert_eq!((explanation.groups_before, explanation.groups_after), (2, 2));
+    }
+
+    #[test]
+    fn blocks_threat() {
+        // the opponent could join up along the edge, the piece on the way
+        // stops it
+        let explanation = explain(&[(0, 0), (1, 1), (6, 5)], &[(7, 7), (7, 4)], (6, 5), (7, 5));
+        assert!(explanation.blocks_threat);
+        assert!(!explanation.wins && !explanation.capture && !explanation.threat);
+    }
+
+    #[test]
+    fn hints() {
+        let rules = Standard::default();
+        let history = GameHistory::new(DrawRules::default());
+        let board = [pieces(&[(0, 0), (1, 1), (3, 0)]), pieces(&[(7, 7), (7, 2)])];
+        let hint = hint_of(&board, &rules, &history, 200, Some(1)).unwrap();
+        // either end of the bottom row joins up
+        assert!(hint.explanation.wins);
+        assert!(hint.value > 0.5f32);
+
+        // nothing to recommend once the game is over
+        let over = [pieces(&[(0, 0), (1, 0)]), pieces(&[(7, 7), (7, 2)])];
+        assert!(hint_of(&over, &rules, &history, 200, Some(1)).is_none());
+    }
+}
@@ -0,0 +1,214 @@
+use super::*;
+
+use rng::splitmix64;
+
+const SIDE_KEY: u64 = 0x9e3779b97f4a7c15u64;
+
+pub trait PositionHash {
+    fn position_hash(&self, ply: i32) -> u64;
+}
+
+impl PositionHash for [u64; 2] {
+    // boards are stored relative to the side to move, so the ply parity is
+    // folded in to tell the two sides apart
+    fn position_hash(&self, ply: i32) -> u64 {
+        let mut hash = if (ply & 1) != 0 { SIDE_KEY } else { 0 };
+        for id in 0..2 {
+            let mut pieces = self[id];
+            while pieces != 0 {
+                let pos = pieces.trailing_zeros() as u64;
+                hash ^= splitmix64(&mut (pos + 64 * id as u64));
+                pieces &= pieces - 1;
+            }
+        }
+        hash
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct DrawRules {
+    // n-fold repetition is a draw, 0 disables
+    pub repetition: u32,
+    // game is drawn after this many moves in total, 0 disables
+    pub move_limit: u32,
+    // how much the search dislikes a draw while it is ahead, and likes one
+    // while it is behind, from 0 (a draw is worth half a win) to 1
+    pub contempt: f32,
+}
+
+#[wasm_bindgen]
+impl DrawRules {
+    #[wasm_bindgen(constructor)]
+    pub fn new(repetition: u32, move_limit: u32, contempt: f32) -> DrawRules {
+        DrawRules {
+            repetition,
+            move_limit,
+            contempt,
+        }
+    }
+}
+
+impl Default for DrawRules {
+    fn default() -> Self {
+        DrawRules {
+            repetition: 3,
+            move_limit: 0,
+            contempt: 0.5f32,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameHistory {
+    // hashes of the positions before the root, oldest first
+    pub hashes: Vec<u64>,
+    pub moves_played: u32,
+    pub rules: DrawRules,
+}
+
+impl GameHistory {
+    pub fn new(rules: DrawRules) -> Self {
+        GameHistory {
+            hashes: vec![],
+            moves_played: 0,
+            rules,
+        }
+    }
+
+    // `history` is a list of sparse boards (`geo.squares()` entries each) of
+    // the positions played before the current one, oldest first, sides
+    // alternating. Boards that do not fit `geo` are left out.
+    pub fn from_sparse(history: &[i32], geo: Geometry, turn: i32, rules: DrawRules) -> Self {
+        let len = history.len() / geo.squares();
+        let hashes = history
+            .chunks(geo.squares())
+            .take(len)
+            .enumerate()
+            .filter_map(|(i, sparse)| {
+                let dist = (len - i) as i32;
+                let board = geo.from_sparse(sparse, turn ^ (dist & 1))?;
+                Some(board.position_hash(-dist))
+            })
+            .collect();
+        GameHistory {
+            hashes,
+            moves_played: len as u32,
+            rules,
+        }
+    }
+
+    // makes `board` (the current root) part of the history, when the root
+    // advances by one move
+    pub fn push(&mut self, board: &[u64; 2]) {
+        // the parity of every stored ply flips with the new root
+        for hash in self.hashes.iter_mut() {
+            *hash ^= SIDE_KEY;
+        }
+        self.hashes.push(board.position_hash(-1));
+        self.moves_played += 1;
+    }
+
+    // `stack` holds the hashes of every position before `hash`, including
+    // the history before the root.
+    pub fn is_draw(&self, stack: &[u64], hash: u64, ply: i32) -> bool {
+        let DrawRules {
+            repetition,
+            move_limit,
+            ..
+        } = self.rules;
+        if move_limit != 0 && self.moves_played + ply as u32 >= move_limit {
+            return true;
+        }
+        repetition != 0
+            && stack.iter().filter(|&&h| h == hash).count() as u32 + 1 >= repetition
+    }
+
+    // value of a draw for the side to move at `ply`, given the current win
+    // rate of the side to move at the root: below half a win while the root
+    // side is ahead, above it while it is behind
+    pub fn draw_value(&self, ply: i32, root_rate: f32) -> f32 {
+        let value = (0.5f32 + self.rules.contempt * (0.5f32 - root_rate)).max(0f32).min(1f32);
+        if (ply & 1) != 0 {
+            1f32 - value
+        } else {
+            value
+        }
+    }
+
+    pub fn path_stack(&self, path: &[&mut SearchNode]) -> Vec<u64> {
+        let mut stack = self.hashes.clone();
+        for (ply, node) in path.iter().enumerate() {
+            if let Some((SearchNodeData::Mid { ref board, .. }, ..)) = node.data {
+                stack.push(board.position_hash(ply as i32));
+            }
+        }
+        stack
+    }
+}
+
+// what one search pass needs to adjudicate draws from the selected node on
+pub(crate) struct DrawContext<'a> {
+    pub history: &'a GameHistory,
+    // hashes of every position before the selected node, see `is_draw`
+    pub stack: Vec<u64>,
+    // of the selected node, the root being 0
+    pub ply: i32,
+    // win rate of the side to move at the root before the pass
+    pub root_rate: f32,
+}
+
+impl<'a> DrawContext<'a> {
+    // `path` runs from the root down to the parent of the selected node
+    pub fn new(history: &'a GameHistory, path: &[&mut SearchNode], root_rate: f32) -> Self {
+        DrawContext {
+            history,
+            stack: history.path_stack(path),
+            ply: path.len() as i32,
+            root_rate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threefold_repetition() {
+        let rules = Standard::default();
+        let start = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        // black and white each step out and back, twice over
+        let cycle = [((1, 0), (1, 2)), ((0, 1), (2, 1)), ((1, 2), (1, 0)), ((2, 1), (0, 1))];
+        let mut history = GameHistory::new(DrawRules::default());
+        let mut board = start;
+        for (i, &(src, dst)) in cycle.iter().cycle().take(8).enumerate() {
+            let (src, dst) = (src.to_coord(), dst.to_coord());
+            assert!(rules.moves(&board).contains(&(src, dst)));
+            history.push(&board);
+            board = rules.apply_move(&board, src, dst);
+            // the start comes back after every cycle, the third time is a draw
+            let draw = history.is_draw(&history.hashes, board.position_hash(0), 0);
+            assert_eq!(draw, i == 7, "after move {}", i + 1);
+        }
+        assert_eq!(board, start);
+    }
+
+    #[test]
+    fn side_to_move_is_part_of_the_hash() {
+        let board = [0b101u64, 0b10u64 << 40];
+        assert_ne!(board.position_hash(0), board.position_hash(1));
+        assert_eq!(board.position_hash(0), board.position_hash(2));
+    }
+
+    #[test]
+    fn draws_follow_the_root_standing() {
+        let history = GameHistory::new(DrawRules::new(3, 0, 0.5f32));
+        assert_eq!(history.draw_value(0, 0.5f32), 0.5f32);
+        // ahead, a draw is worth less than half a win, behind more
+        assert!(history.draw_value(0, 0.8f32) < 0.5f32);
+        assert!(history.draw_value(0, 0.2f32) > 0.5f32);
+        // and the other way round for the opponent
+        assert_eq!(history.draw_value(1, 0.8f32), 1f32 - history.draw_value(0, 0.8f32));
+    }
+}
@@ -8,15 +8,100 @@ extern crate serde_derive;
 extern crate serde;
 use serde::ser::{self, Impossible, Serialize, SerializeMap, SerializeStruct, Serializer};
 
+#[cfg(target_arch = "wasm32")]
 extern crate wbg_rand;
-use wbg_rand::{wasm_rng, Rng, WasmRng};
 
 use std::mem;
 use std::ops::{Generator, GeneratorState};
 use std::pin::Pin;
 
+mod rng;
+use rng::RngProvider;
+
+mod geometry;
+pub use geometry::Geometry;
+
+mod bitboard;
+pub use bitboard::{group_analysis, BitBoard, BoundingBox};
+
+mod rules;
+pub use rules::{BlackHole, NoCapture, Rules, Standard, Variant};
+
+mod history;
+pub use history::{DrawRules, GameHistory};
+use history::{DrawContext, PositionHash};
+
+mod query;
+pub use query::{game_status, is_legal_move, legal_destinations, legal_moves};
+
+mod result;
+pub use result::{ChildStat, SearchResult, SearchStats};
+
+mod analysis;
+pub use analysis::{analyse, analyse_position, PvLine, Proven};
+
+mod session;
+pub use session::SearchSession;
+
+mod worker;
+pub use worker::{Progress, SearchControl};
+use session::TIME_CHECK_INTERVAL;
+
+mod notation;
+pub use notation::{
+    move_name, parse_move, parse_position, parse_record, parse_square, position_name, record_name,
+    square_name,
+};
+
+mod setup;
+pub use setup::{parse_position_string, position_string, start_position, Setup};
+
+mod book;
+pub use book::{load_opening_book, unload_opening_book, BookBuilder, BookEntry, BookError, OpeningBook};
+
+mod pns;
+pub use pns::{pn_search, solve_position, PnResult};
+
+mod tactics;
+
+mod export;
+pub use export::TreeSnapshot;
+
+mod save;
+pub use save::TreeError;
+
+mod heatmap;
+pub use heatmap::{Heatmap, MoveArrow};
+
+mod review;
+pub use review::{
+    analyse_game, analyse_game_record, GameAnalysis, GameAnalysisConfig, MoveAnalysis, MoveQuality,
+};
+
+mod hint;
+pub use hint::{explain, explain_move, hint, hint_of, Hint, MoveExplanation};
+
+mod policy;
+pub use policy::{my_plain_solution_policy, search_with_policy, FinalPolicy};
+
+mod strength;
+pub use strength::{
+    choose_move, level_elo, level_strength, my_plain_solution_level, play_match,
+    play_with_strength, Level, MatchScore, Strength,
+};
+
+mod puzzle;
+pub use puzzle::{
+    generate_puzzle_text, generate_puzzles, shortest_win, winning_moves, winning_moves_of, Puzzle,
+};
+
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "parallel")]
+pub use parallel::{parallel_search, ParallelConfig, ParallelMode};
+
 #[wasm_bindgen]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Move {
     pub x0: i32,
     pub y0: i32,
@@ -24,6 +109,22 @@ pub struct Move {
     pub y1: i32,
 }
 
+#[wasm_bindgen]
+impl Move {
+    #[wasm_bindgen(constructor)]
+    pub fn new(x0: i32, y0: i32, x1: i32, y1: i32) -> Move {
+        Move { x0, y0, x1, y1 }
+    }
+}
+
+impl Move {
+    fn from_coords(src: i32, dst: i32) -> Move {
+        let (x0, y0) = src.to_coord_2d();
+        let (x1, y1) = dst.to_coord_2d();
+        Move { x0, y0, x1, y1 }
+    }
+}
+
 #[wasm_bindgen]
 extern "C" {
     fn alert(x: &str);
@@ -31,6 +132,26 @@ extern "C" {
     fn log_tree(x: &JsValue);
 }
 
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = Date)]
+    fn now() -> f64;
+}
+
+// milliseconds since some fixed point in time
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 * 1e-6
+}
+
 const ROW: u64 = 0xFFu64;
 const COL: u64 = 0x0101010101010101u64;
 const SLASH0: u64 = 0x0102040810204080u64;
@@ -46,401 +167,30 @@ const SIMULATE_COUNT: u32 = 16; //32;
 
 const EMPTY_MOVE: (i32, i32) = (100, 100);
 
-const RNG_PHASE: u32 = 8;
-const RNG_SHAMT: u32 = 8;
-const RNG_MASK: u32 = (1u32 << RNG_SHAMT) - 1;
-
-const FAKE_RNG_LEN: usize = 1 << 8;
-const FAKE_RNG_VALUES: [u64; FAKE_RNG_LEN] = [
-    1676761424638520202,
-    2939895356340538458,
-    17728998469973128227,
-    4873731339148202259,
-    14888766267554033333,
-    3397190989610969540,
-    17016120907035480532,
-    5720051754036445887,
-    9226006198283208365,
-    4018310429034958582,
-    12932234442750983849,
-    1561155504280919192,
-    13898158795496324787,
-    5715089962962972963,
-    1079756092685224277,
-    7898850948263649175,
-    5921989576063733925,
-    16504417086336376473,
-    4240952514127547152,
-    13892607808829003127,
-    9913266533438410728,
-    12171393552861043390,
-    3757904714689556701,
-    8799141626483857099,
-    15112476009282929885,
-    12056093053672559491,
-    14997789461864109048,
-    6279772964424601346,
-    3308669982062096412,
-    16994103614101904274,
-    18413575972931516964,
-    10392610048028876503,
-    18086827500338154459,
-    4608828808289729298,
-    14061891413303642155,
-    16288506097374620300,
-    11517521245459201017,
-    12151372652580226307,
-    9235720325026900645,
-    7433823344142084156,
-    6571722528565114074,
-    18313037719398340516,
-    14957875857370919346,
-    2497512302106918145,
-    2604686494671587446,
-    3508265945822672164,
-    14403833058561485841,
-    7049299856786905296,
-    2163235560049824351,
-    13838320476001757569,
-    17695574168459992,
-    16680976756210633072,
-    13098763226352090362,
-    9491239379368690248,
-    1963545842821512784,
-    9903313772432692403,
-    16728806772645202475,
-    13738229821777006309,
-    6334718664342137367,
-    11536767335736030956,
-    15364385787576022026,
-    15910285379767651386,
-    10066837523738016639,
-    11785278039674685990,
-    30956770070838388,
-    5471559825518861608,
-    552543316267517069,
-    13615649174442901335,
-    3766134670152004356,
-    2032874038834920947,
-    18225546607226973706,
-    17182677365428888235,
-    14152803445638057208,
-    5265703220483582023,
-    3220387302988544660,
-    2606273476983589983,
-    9149205774268822573,
-    10955844135931433785,
-    2193109761341758610,
-    3404059774305765462,
-    707381209765997804,
-    8895733017833558147,
-    16120688406502153905,
-    2768260120132862439,
-    12472295333486694327,
-    13963903900262325044,
-    7452253811085463924,
-    940048371681649676,
-    3209322651065194433,
-    9793723033965714244,
-    12510740029007402601,
-    16227047529442877283,
-    4303459429779168975,
-    393000640965451993,
-    11986793946204985917,
-    12802571012373768498,
-    3141959344722538689,
-    6919762220100225400,
-    10964311322043469355,
-    3511652053681691308,
-    844570772520343791,
-    404117342785637878,
-    7580705639920345507,
-    8181479297670164124,
-    2144102592099176944,
-    6300201431409044071,
-    8317076033381107516,
-    4665244675622328608,
-    6051988362316902582,
-    15824127020801728259,
-    5583106237902089642,
-    12909847312603412095,
-    13153371221269165188,
-    14308666500783577712,
-    1559139881421591123,
-    9610260245941585539,
-    17110392190811749880,
-    5938100763269148410,
-    4623769724401529823,
-    10029177695292702044,
-    2258689528387236946,
-    2095433011696979013,
-    11171207816933134132,
-    9936139159715991650,
-    13283674792752983853,
-    4245403495737788709,
-    10225615215342202676,
-    13589633007058903406,
-    477038050453562113,
-    2157577791336328351,
-    15039045358678315873,
-    3181686648079685452,
-    1711047192750385117,
-    11840595698236494011,
-    652530361805992006,
-    1235398577916225669,
-    5544741489090107245,
-    8954026480920709355,
-    3904546910116376838,
-    14122053271804872947,
-    1197998269244372091,
-    11640542567916952696,
-    13106655145976716629,
-    3307454261830868710,
-    12852845119402631043,
-    8741307047886469612,
-    6258499328920920952,
-    17128120147247571144,
-    9490803404556694735,
-    1290107018526767447,
-    15563807295763094433,
-    3240736257704658728,
-    7784419869505931903,
-    12831339338296753344,
-    5724645739582991588,
-    5059088715799566884,
-    2255478945871969242,
-    6864669823810819636,
-    18093549901541662719,
-    10025586063300516853,
-    10690778138020981677,
-    17124894572556081551,
-    9063309290606003608,
-    14135282153307452154,
-    16918576790600213037,
-    1406440462984585053,
-    17548341056499848913,
-    1491564791923457376,
-    7910795817404609775,
-    18016257755022730265,
-    14053186136624006838,
-    8345490850787587698,
-    14972524401579471279,
-    1346546449563778970,
-    4520688261236579144,
-    15509445197340960776,
-    5608540929518366295,
-    12388752117392234366,
-    680602229358171668,
-    18429351960167797626,
-    4716689556165444143,
-    11671962534772679628,
-    1394210961703736585,
-    13175217261993577715,
-    3320535918747679969,
-    8173034598410817480,
-    16089642607628774139,
-    1254673656779780414,
-    16014920622290801524,
-    12223474832482448005,
-    3751115562845375103,
-    252653078596294799,
-    11817532774757893581,
-    6568795886591900286,
-    1545590643709047235,
-    10078078057595492494,
-    3469276706308673442,
-    11345670332764466485,
-    11166722010043560901,
-    11231306468591108878,
-    8387053425303657539,
-    16530469037217536487,
-    5115453863221368819,
-    11215048310092129523,
-    285072285072496416,
-    18158150010293132677,
-    15944440813467023751,
-    16225020464807301890,
-    3015221707980462495,
-    16427170926547517685,
-    7746194321968066461,
-    3611413298331730877,
-    14854393899312352040,
-    2046394030286510469,
-    6115642871432519136,
-    17283079244241396190,
-    1064212136418418429,
-    2384707396599419629,
-    3465969274424065281,
-    6654147952765748330,
-    1753795585333893179,
-    14843680762529132267,
-    1778859148707539532,
-    915900562075528480,
-    13735947414674269624,
-    12153294834607201986,
-    3705577077924774982,
-    17233341745947383405,
-    9306836491618306671,
-    1666746922907925419,
-    12270798747298758276,
-    1503963713510928470,
-    6874113541115680784,
-    11782526596220135258,
-    1783061059702411232,
-    11616125168708785948,
-    11397644361446777381,
-    146103362511728521,
-    16260865860763507098,
-    16220466138079195235,
-    13097422310252406661,
-    16167328130239623222,
-    5022862876868630699,
-    8600129242113030533,
-    7742615801448773175,
-    1481756419612638737,
-    16314200924770581972,
-    11925725101382370362,
-    15924520858787191462,
-    5980704901846371251,
-    790112647235428245,
-    16089511475710765047,
-    172160118696180607,
-    39550878273559962,
-    8397461583374611443,
-    3546525223732628155,
-];
-
-trait U64Rng {
-    fn next(&mut self) -> u64;
-    fn new() -> Self;
-}
-
-impl U64Rng for WasmRng {
-    fn next(&mut self) -> u64 {
-        self.next_u64()
-    }
-    fn new() -> Self {
-        wasm_rng()
-    }
-}
-
-struct MyRng<T: U64Rng> {
-    curr: u64,
-    phase: u32,
-    rng: T,
-}
-
-struct FakeRng {
-    phase: usize,
-}
-
-impl U64Rng for FakeRng {
-    fn next(&mut self) -> u64 {
-        self.phase = (self.phase + 1) & (FAKE_RNG_LEN - 1);
-        FAKE_RNG_VALUES[self.phase]
-    }
-    fn new() -> Self {
-        FakeRng {
-            phase: WasmRng::new().next_u32() as usize & (FAKE_RNG_LEN - 1),
-        }
-    }
-}
-
-impl<T: U64Rng> MyRng<T> {
-    fn next_u32(&mut self) -> u32 {
-        if self.phase == 0 {
-            self.curr = self.rng.next();
-        }
-        self.phase = (self.phase + 1) & (RNG_PHASE - 1);
-        let val = self.curr as u32 & RNG_MASK;
-        self.curr >>= RNG_SHAMT;
-        val
-    }
-    fn new() -> Self {
-        MyRng {
-            curr: 0,
-            phase: 0,
-            rng: T::new(),
-        }
-    }
-}
-
-type RngProvider = MyRng<FakeRng>;
-
-trait BitBoard {
-    fn eight_conn_expand(self) -> Self;
-}
-
-impl BitBoard for u64 {
-    fn eight_conn_expand(self) -> Self {
-        let line = self | self.overflowing_shl(8).0 | self.overflowing_shr(8).0;
-        line | (line & RM_LEFT).overflowing_shl(1).0 | (line & RM_RIGHT).overflowing_shr(1).0
-    }
-}
-
 trait Board {
-    fn from_sparse_board(sparse: &[i32], turn: i32) -> Self;
     fn apply_move(&self, src: i32, dst: i32) -> Self;
+    fn is_connected(&self, id: usize) -> bool;
     fn is_win_state(&self) -> Option<bool>;
-    fn gen_rand_move(&self, turn: i32, rng: &mut RngProvider) -> (i32, i32);
 }
 
 impl Board for [u64; 2] {
-    fn from_sparse_board(sparse: &[i32], turn: i32) -> Self {
-        let mut board = [0u64; 2];
-        for i in 0..8 {
-            for j in 0..8 {
-                let id = sparse[j + i * 8];
-                if id >= 0 {
-                    board[id as usize] |= 1u64.overflowing_shl((j + 8 * i) as u32).0;
-                }
-            }
-        }
-        if turn == 0 {
-            board
-        } else {
-            [board[1], board[0]]
-        }
-    }
     fn apply_move(&self, src: i32, dst: i32) -> Self {
         [
             self[1] & !dst.to_piece(),
             self[0] & !src.to_piece() | dst.to_piece(),
         ]
     }
+    fn is_connected(&self, id: usize) -> bool {
+        if self[1 - id].count_ones() == 1 {
+            return true;
+        }
+        self[id].is_one_group()
+    }
+    // for the side to move; if both sides are connected, it has won
     fn is_win_state(&self) -> Option<bool> {
 
-        let check = |id: usize| -> bool {
-            if self[1 - id].count_ones() == 1 {
-                return true;
-            }
-            let now = self[id];
-            let mut conn: u64 = 0;
-
-            for pos in 0..64 {
-                if (now & pos.to_piece()) != 0 {
-                    // pieces.push(pos);
-                    conn = pos.to_piece();
-                    break;
-                }
-            }
-
-            if conn != 0 {
-                loop {
-                    let new_conn = now & conn.eight_conn_expand();
-                    if new_conn == conn {
-                        break;
-                    }
-                    conn = now & new_conn;
-                }
-                conn == now
-            } else {
-                false
-            }
-        };
-
-        let win = check(0);
-        let lose = check(1);
+        let win = self.is_connected(0);
+        let lose = self.is_connected(1);
 
         if !win && !lose {
             None
@@ -448,27 +198,6 @@ impl Board for [u64; 2] {
             Some(win)
         }
     }
-    fn gen_rand_move(&self, turn: i32, rng: &mut RngProvider) -> (i32, i32) {
-
-        let rn: i32 = rng.next_u32() as i32 & 0x3fi32;
-        let b = self[turn as usize];
-
-        // random piece from rn
-        for i in (rn..64).filter(|i| (i.to_piece() & b) != 0) {
-            let mut moves = gen_moves(&self, turn, i);
-            if let GeneratorState::Yielded(dst) = Pin::new(&mut moves).resume() {
-                return (i, dst);
-            }
-        }
-        for i in (0..rn).filter(|i| (i.to_piece() & b) != 0) {
-            let mut moves = gen_moves(&self, turn, i);
-            if let GeneratorState::Yielded(dst) = Pin::new(&mut moves).resume() {
-                return (i, dst);
-            }
-        }
-
-        EMPTY_MOVE
-    }
 }
 
 trait Coord {
@@ -505,6 +234,7 @@ impl Coord2D for (i32, i32) {
 
 fn gen_moves<'a>(
     board: &'a [u64; 2],
+    geo: Geometry,
     turn: i32,
     pos: i32,
 ) -> impl Generator<Yield = i32, Return = ()> + 'a {
@@ -519,14 +249,15 @@ fn gen_moves<'a>(
         let (x, y) = pos.to_coord_2d();
         let tot = board[0] | board[1];
         let oppo = board[(1 - turn) as usize];
+        let (n, area) = (geo.size(), geo.area());
 
         {
-            let col = COL.overflowing_shl(x as u32).0;
+            let col = COL.overflowing_shl(x as u32).0 & area;
             let col_cnt = (tot & col).count_ones() as i32;
 
             let top = pos + (col_cnt.overflowing_shl(3).0);
             let bottom = pos - (col_cnt.overflowing_shl(3).0);
-            if top < 64 && check(top) {
+            if y + col_cnt < n && check(top) {
                 if (!(top.higher_eq() | pos.lower_eq()) & col & oppo) == 0 {
                     yield top;
                 }
@@ -538,12 +269,12 @@ fn gen_moves<'a>(
             }
         }
         {
-            let row = ROW.overflowing_shl(y.overflowing_shl(3).0 as u32).0;
+            let row = ROW.overflowing_shl(y.overflowing_shl(3).0 as u32).0 & area;
             let row_cnt = (tot & row).count_ones() as i32;
 
             let left = pos + row_cnt;
             let right = pos - row_cnt;
-            if x + row_cnt < 8 && check(left) {
+            if x + row_cnt < n && check(left) {
                 if (!(left.higher_eq() | pos.lower_eq()) & row & oppo) == 0 {
                     yield left;
                 }
@@ -564,17 +295,17 @@ fn gen_moves<'a>(
                 SLASH0
                     .overflowing_shl((pl - 7).overflowing_shl(3).0 as u32)
                     .0
-            };
+            } & area;
             let slash_cnt = (tot & slash).count_ones() as i32;
 
             let top_right = pos + (7 * slash_cnt);
             let bottom_left = pos - (7 * slash_cnt);
-            if x - slash_cnt >= 0 && y + slash_cnt < 8 && check(top_right) {
+            if x - slash_cnt >= 0 && y + slash_cnt < n && check(top_right) {
                 if (!(top_right.higher_eq() | pos.lower_eq()) & slash & oppo) == 0 {
                     yield top_right;
                 }
             }
-            if x + slash_cnt < 8 && y - slash_cnt >= 0 && check(bottom_left) {
+            if x + slash_cnt < n && y - slash_cnt >= 0 && check(bottom_left) {
                 if (!(pos.higher_eq() | bottom_left.lower_eq()) & slash & oppo) == 0 {
                     yield bottom_left;
                 }
@@ -586,12 +317,12 @@ fn gen_moves<'a>(
                 SLASH1.overflowing_shr(pl.overflowing_shl(3).0 as u32).0
             } else {
                 SLASH1.overflowing_shl(-pl.overflowing_shl(3).0 as u32).0
-            };
+            } & area;
             let slash_cnt = (tot & slash).count_ones() as i32;
 
             let top_left = pos + (9 * slash_cnt);
             let bottom_right = pos - (9 * slash_cnt);
-            if x + slash_cnt < 8 && y + slash_cnt < 8 && check(top_left) {
+            if x + slash_cnt < n && y + slash_cnt < n && check(top_left) {
                 if (!(top_left.higher_eq() | pos.lower_eq()) & slash & oppo) == 0 {
                     yield top_left;
                 }
@@ -605,23 +336,6 @@ fn gen_moves<'a>(
     }
 }
 
-fn gen_all_moves<'a>(
-    board: &'a [u64; 2],
-    turn: i32,
-) -> impl Generator<Yield = (i32, i32), Return = ()> + 'a {
-    move || {
-        let tot = board[turn as usize];
-        for pos in 0..64 {
-            if (tot & pos.to_piece()) != 0 {
-                let mut moves = gen_moves(&board, turn, pos);
-                while let GeneratorState::Yielded(dst) = Pin::new(&mut moves).resume() {
-                    yield (pos, dst)
-                }
-            }
-        }
-    }
-}
-
 #[derive(Debug)]
 pub struct SearchNode {
     pub curr_move: (i32, i32),
@@ -637,6 +351,7 @@ pub enum SearchNodeData {
         childs: Vec<SearchNode>,
     },
     Term(bool),
+    Draw,
 }
 
 impl Serialize for SearchNode {
@@ -687,6 +402,7 @@ impl Serialize for SearchNodeData {
                 state.end()
             }
             SearchNodeData::Term(win) => serializer.serialize_bool(*win),
+            SearchNodeData::Draw => serializer.serialize_str("draw"),
         }
     }
 }
@@ -694,40 +410,74 @@ impl Serialize for SearchNodeData {
 #[derive(Debug)]
 struct MCTSSearchPass {
     hit: bool,
-    win: bool,
+    value: f32,
     expand_term: bool,
     expand_depth: i32,
-    simulate_depth: i32,
+    // histogram of the playout lengths of this pass
+    simulate_depths: [u32; MAX_STEP + 1],
+    undecided: u32,
 }
 
 impl SearchNode {
 
-    fn expand(&mut self, board: &[u64; 2], debug: bool) -> bool {
+    // a fresh root for `board`, with a move the solver proves winning marked
+    pub fn root<R: Rules>(board: &[u64; 2], rules: &R, history: &GameHistory) -> Self {
+        let mut root = SearchNode {
+            curr_move: EMPTY_MOVE,
+            data: Some((SearchNodeData::from(board, rules), 0f32, 0f32)),
+        };
+        root.solve_root(board, rules, history);
+        root.screen_root(board, rules);
+        root
+    }
+
+    fn expand<R: Rules>(
+        &mut self,
+        board: &[u64; 2],
+        rules: &R,
+        draws: &DrawContext,
+        debug: bool,
+    ) -> bool {
 
         if let None = self.data {
             let (src, dst) = self.curr_move;
-            let new_board = board.apply_move(src, dst);
-            self.data = Some((SearchNodeData::from(&new_board), 0f32, 0f32));
+            let new_board = rules.apply_move(board, src, dst);
+            let DrawContext {
+                history,
+                ref stack,
+                ply,
+                ..
+            } = *draws;
+            self.data = Some((
+                SearchNodeData::with_history(&new_board, rules, history, stack, ply),
+                0f32,
+                0f32,
+            ));
         }
 
-        if let Some((SearchNodeData::Term(..), ..)) = self.data {
-            true
-        } else {
-            false
+        match self.data {
+            Some((SearchNodeData::Term(..), ..)) | Some((SearchNodeData::Draw, ..)) => true,
+            _ => false,
         }
 
     }
 
+    // a move proven to lose (the opponent has won after it, see
+    // `screen_root`) is only taken when every other move loses as well
     fn find_max(&mut self, bare: bool) -> Option<*mut SearchNode> {
 
         let mut max_node: Option<*mut SearchNode> = None;
         let (ref mut data, _a0, b0) = self.data.as_mut().unwrap();
         match data {
             SearchNodeData::Mid { ref mut childs, .. } => {
-                let mut max_fact = -1f32;
+                let mut max_fact = (false, -1f32);
                 for child in childs.iter_mut() {
                     if child.data.is_some() {
-                        let (.., a1, b1) = child.data.as_ref().unwrap();
+                        let (ref data1, a1, b1) = child.data.as_ref().unwrap();
+                        let open = match data1 {
+                            SearchNodeData::Term(true) => false,
+                            _ => true,
+                        };
 
                         let fact = 1f32 - a1 / b1
                             + if bare {
@@ -736,8 +486,8 @@ impl SearchNode {
                                 C * (b0.ln() / b1).sqrt()
                             };
 
-                        if fact > max_fact {
-                            max_fact = fact;
+                        if (open, fact) > max_fact {
+                            max_fact = (open, fact);
                             max_node = Some(child);
                         }
                     }
@@ -745,6 +495,7 @@ impl SearchNode {
                 }
             }
             SearchNodeData::Term(_win) => {} //
+            SearchNodeData::Draw => {}
         }
 
         max_node
@@ -804,8 +555,8 @@ impl SearchNode {
                             }
                         }
                     }
-                    // win node
-                    Some((SearchNodeData::Term(win), ..)) => {
+                    // win / draw node
+                    Some((SearchNodeData::Term(..), ..)) | Some((SearchNodeData::Draw, ..)) => {
                         // this node
                         return (Some((&mut *node_ptr, *board_ptr)), path);
                     }
@@ -814,57 +565,79 @@ impl SearchNode {
         }
     }
 
-    fn simulate(&self, rng: &mut RngProvider, debug: bool) -> (Option<bool>, i32) {
+    // returns the playout value for the side to move at this node
+    fn simulate<R: Rules>(
+        &self,
+        rules: &R,
+        rng: &mut RngProvider,
+        draws: &mut DrawContext,
+        debug: bool,
+    ) -> (Option<f32>, i32) {
 
         let (ref data, ..) = self.data.as_ref().unwrap();
+        let (history, ply, root_rate) = (draws.history, draws.ply, draws.root_rate);
+        let stack = &mut draws.stack;
 
         match data {
             SearchNodeData::Mid { ref board, .. } => {
                 let mut curr_board: [u64; 2] = *board;
+                let base = stack.len();
+                let mut res = (None, MAX_STEP as i32);
 
                 for step in 0..MAX_STEP {
                     // if debug {
                     //     alert(&format!("0> {:?}", curr_board));
                     // }
 
-                    match curr_board.is_win_state() {
+                    match rules.is_win_state(&curr_board) {
                         Some(win) => {
 
                             // if debug {
                             //     alert(&format!("1> {:?}", win));
                             // }
 
-                            return (Some(win ^ ((step & 1) != 0)), step as i32);
+                            let win = win ^ ((step & 1) != 0);
+                            res = (Some(if win { 1f32 } else { 0f32 }), step as i32);
+                            break;
                         }
                         _ => {
                             // if debug {
                             //     alert(&format!("2> {:?}", curr_board));
                             // }
 
-                            let (src, dst) = curr_board.gen_rand_move(0, rng);
-                            curr_board = curr_board.apply_move(src, dst);
+                            let curr_ply = ply + step as i32;
+                            let hash = curr_board.position_hash(curr_ply);
+                            if history.is_draw(stack, hash, curr_ply) {
+                                res = (Some(history.draw_value(ply, root_rate)), step as i32);
+                                break;
+                            }
+                            stack.push(hash);
+
+                            let (src, dst) = rules.rand_move(&curr_board, rng);
+                            curr_board = rules.apply_move(&curr_board, src, dst);
                         }
                     }
                 }
-                (None, MAX_STEP as i32)
+
+                stack.truncate(base);
+                res
             }
             SearchNodeData::Term(win) => {
                 // if debug {
                 //     alert(&format!("3> {:?}", *self));
                 // }
-                return (Some(*win), 0);
+                return (Some(if *win { 1f32 } else { 0f32 }), 0);
             }
+            SearchNodeData::Draw => (Some(history.draw_value(ply, root_rate)), 0),
         }
     }
 
-    fn back_propagate(&mut self, win: bool) {
+    fn back_propagate(&mut self, value: f32) {
 
         let (.., ref mut a, ref mut b) = self.data.as_mut().unwrap();
 
         *b += 1f32;
-        if win {
-            *a += 1f32;
-        }
+        *a += value;
 
     }
 
@@ -872,22 +645,43 @@ impl SearchNode {
 
 impl SearchNodeData {
 
-    fn from(board: &[u64; 2]) -> Self {
+    // like `from`, but adjudicates repetitions and the move limit
+    fn with_history<R: Rules>(
+        board: &[u64; 2],
+        rules: &R,
+        history: &GameHistory,
+        stack: &[u64],
+        ply: i32,
+    ) -> Self {
+
+        if rules.is_win_state(board).is_none()
+            && history.is_draw(stack, board.position_hash(ply), ply)
+        {
+            return SearchNodeData::Draw;
+        }
+
+        if let Some(data) = SearchNodeData::solved(board, rules, history, stack, ply) {
+            return data;
+        }
+
+        SearchNodeData::from(board, rules)
+    }
+
+    fn from<R: Rules>(board: &[u64; 2], rules: &R) -> Self {
 
-        if let Some(win) = board.is_win_state() {
+        if let Some(win) = rules.is_win_state(board) {
             // alert(&format!("board = {:?}", board));
             return SearchNodeData::Term(win);
         }
 
-        let mut all_moves_vec = vec![];
-        let mut all_moves = gen_all_moves(board, 0);
-
-        while let GeneratorState::Yielded((src, dst)) = Pin::new(&mut all_moves).resume() {
-            all_moves_vec.push(SearchNode {
-                curr_move: (src, dst),
+        let all_moves_vec: Vec<SearchNode> = rules
+            .moves(board)
+            .into_iter()
+            .map(|curr_move| SearchNode {
+                curr_move,
                 data: None,
             })
-        }
+            .collect();
 
         let len = all_moves_vec.len();
 
@@ -900,9 +694,41 @@ impl SearchNodeData {
     }
 }
 
-fn mcts_search_pass(
+// a proven leaf at the end of `path` may prove its ancestors
+fn propagate_term(path: &mut [&mut SearchNode], mut win: bool) {
+    for node in path.iter_mut().skip(1).rev().skip(1) {
+        let (_data, _a, b) = node.data.as_mut().unwrap();
+        win = !win;
+        if win {
+            // one child lose, father win
+            node.data = Some((SearchNodeData::Term(win), *b, *b));
+        } else {
+            // if let SearchNodeData::Mid { ref childs, .. } = data {
+            //     if childs.iter().all(|child| {
+            //         if let Some((SearchNodeData::Term(true), ..), ..) = child.data {
+            //             true
+            //         } else {
+            //             false
+            //         }
+            //     }) {
+            //         // all child win, father lose
+            //         node.data = Some((SearchNodeData::Term(win), 0f32, *b));
+            //         // no this node is not affected
+            //         break;
+            //     }
+            // } else {
+            //     panic!();
+            // }
+            break;
+        }
+    }
+}
+
+fn mcts_search_pass<R: Rules>(
     root: &mut SearchNode,
     board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
     rng: &mut RngProvider,
     debug: bool,
 ) -> MCTSSearchPass {
@@ -913,12 +739,15 @@ fn mcts_search_pass(
 
     // log(&format!("select"));
 
+    // draws are scored against the root's standing before this pass
+    let root_rate = root.win_rate();
     let (sel_res, mut path) = root.select(board, debug);
-    let mut winn: bool = false;
+    let mut value: f32 = 0f32;
     let mut res: bool = false;
     let mut expand_term = false;
     let expand_depth = path.len() as i32;
-    let mut simulate_depth = 0;
+    let mut simulate_depths = [0u32; MAX_STEP + 1];
+    let mut undecided = 0;
 
     if let Some((curr_node, curr_board)) = sel_res {
 
@@ -933,40 +762,16 @@ fn mcts_search_pass(
                 dst.to_coord_2d()
             ))
         }
+        // positions before the selected node, the node itself is the last one in path
+        let mut draws = DrawContext::new(history, &path[..path.len() - 1], root_rate);
+
         // log(&format!("expand"));
-        expand_term = curr_node.expand(&curr_board, debug);
-        if expand_term {
-            if let (SearchNodeData::Term(mut win), ..) = curr_node.data.as_ref().unwrap() {
-                for node in path.iter_mut().skip(1).rev().skip(1) {
-                    let (data, a, b) = node.data.as_mut().unwrap();
-                    win = !win;
-                    if win {
-                        // one child lose, father win
-                        node.data = Some((SearchNodeData::Term(win), *b, *b));
-                    } else {
-                        // if let SearchNodeData::Mid { ref childs, .. } = data {
-                        //     if childs.iter().all(|child| {
-                        //         if let Some((SearchNodeData::Term(true), ..), ..) = child.data {
-                        //             true
-                        //         } else {
-                        //             false
-                        //         }
-                        //     }) {
-                        //         // all child win, father lose
-                        //         node.data = Some((SearchNodeData::Term(win), 0f32, *b));
-                        //         // no this node is not affected
-                        //         break;
-                        //     }
-                        // } else {
-                        //     panic!();
-                        // }
-                        break;
-                    }
-                }
-            } else {
-                panic!();
-            }
-        }
+        expand_term = curr_node.expand(&curr_board, rules, &draws, debug);
+        let term_win = match curr_node.data.as_ref().unwrap() {
+            // draws are not propagated
+            (SearchNodeData::Term(win), ..) => Some(*win),
+            _ => None,
+        };
 
         if debug {
             log(&format!("expanded node: {:?}", curr_node))
@@ -974,24 +779,32 @@ fn mcts_search_pass(
 
         for i in 0..SIMULATE_COUNT {
             // log(&format!("simulate"));
-            let (simulate_res, simulate_dep) = curr_node.simulate(rng, debug);
-            simulate_depth += simulate_dep;
-            if let Some(win) = simulate_res {
-                winn = win;
+            let (simulate_res, simulate_dep) =
+                curr_node.simulate(rules, rng, &mut draws, debug);
+            simulate_depths[simulate_dep as usize] += 1;
+            if let Some(v) = simulate_res {
+                value = v;
                 res = true;
+            } else {
+                undecided += 1;
             }
             // log(&format!("back propagate"));
             for node in path.iter_mut().rev() {
-                node.back_propagate(winn);
-                winn = !winn;
+                node.back_propagate(value);
+                value = 1f32 - value;
             }
             // log(&format!("back propagate ok"));
         }
 
+        // last, as proving an ancestor drops the subtree below it
+        if let Some(win) = term_win {
+            propagate_term(&mut path, win);
+        }
+
         if debug {
             log(&format!(
-                "simulated node: finish: {:?} win: {:?}",
-                res, winn
+                "simulated node: finish: {:?} value: {:?}",
+                res, value
             ))
         }
 
@@ -1003,62 +816,137 @@ fn mcts_search_pass(
 
     MCTSSearchPass {
         hit: res,
-        win: !winn,
+        value: 1f32 - value,
         expand_term,
         expand_depth,
-        simulate_depth,
+        simulate_depths,
+        undecided,
+    }
+}
+
+
+// the search is reproducible for a given `seed`, random if it is omitted;
+// None if `sparse` is not an 8x8 board or there is no move to search. The
+// search is only logged if `verbose` is set.
+#[wasm_bindgen]
+pub fn my_plain_solution(
+    turn: i32,
+    sparse: &[i32],
+    seed: Option<u32>,
+    verbose: Option<bool>,
+) -> Option<Move> {
+    my_plain_solution_with_history(turn, sparse, &[], &DrawRules::default(), seed, verbose)
+}
+
+// `history` holds the sparse boards of all positions before `sparse`, oldest
+// first, so repetitions and the move limit can be adjudicated as draws.
+#[wasm_bindgen]
+pub fn my_plain_solution_with_history(
+    turn: i32,
+    sparse: &[i32],
+    history: &[i32],
+    rules: &DrawRules,
+    seed: Option<u32>,
+    verbose: Option<bool>,
+) -> Option<Move> {
+
+    let board = Geometry::default().from_sparse(sparse, turn)?;
+    let history = GameHistory::from_sparse(history, Geometry::default(), turn, *rules);
+    let seed = seed.map(u64::from);
+
+    if let Some((src, dst)) = book::book_move(&board, &mut RngProvider::with_seed(seed)) {
+        return Some(Move::from_coords(src, dst));
     }
+
+    let verbose = verbose.unwrap_or(false);
+    let result = search(&board, &Standard::default(), &history, MAX_NODE, seed, verbose)?;
+    Some(result.best)
 }
 
+// `my_plain_solution` on a `size` x `size` board, `sparse` has `size * size`
+// entries; None if they do not match
+#[wasm_bindgen]
+pub fn my_plain_solution_sized(
+    turn: i32,
+    sparse: &[i32],
+    size: i32,
+    seed: Option<u32>,
+    verbose: Option<bool>,
+) -> Option<Move> {
+    my_plain_solution_variant(turn, sparse, size, "standard", seed, verbose)
+}
 
+// `my_plain_solution_sized` under the rules of `variant`, see
+// `Variant::from_name`; None if it is unknown. The opening book is only
+// looked up for the standard game on the standard board.
 #[wasm_bindgen]
-pub fn my_plain_solution(turn: i32, sparse: &[i32]) -> Move {
-
-    let board = <[u64; 2]>::from_sparse_board(sparse, turn);
-    let mut rng = MyRng::new();
-    // let mut rng = FakeRng::new(1024);
-    let mut root = SearchNode {
-        curr_move: EMPTY_MOVE,
-        data: Some((SearchNodeData::from(&board), 0f32, 0f32)),
+pub fn my_plain_solution_variant(
+    turn: i32,
+    sparse: &[i32],
+    size: i32,
+    variant: &str,
+    seed: Option<u32>,
+    verbose: Option<bool>,
+) -> Option<Move> {
+
+    let geo = Geometry::new(size)?;
+    let rules = Variant::from_name(variant, geo)?;
+    let board = geo.from_sparse(sparse, turn)?;
+    let history = GameHistory::new(DrawRules::default());
+    let seed = seed.map(u64::from);
+
+    let mut rng = RngProvider::with_seed(seed);
+    if let Some((src, dst)) = book::variant_book_move(&board, &rules, &mut rng) {
+        return Some(Move::from_coords(src, dst));
+    }
+
+    let result = search(&board, &rules, &history, MAX_NODE, seed, verbose.unwrap_or(false))?;
+    Some(result.best)
+}
+
+// same as `my_plain_solution_with_history`, but returns the whole
+// `SearchResult` as a plain object, logging only if `verbose` is set; null
+// if `sparse` is not an 8x8 board or there is no move to search
+#[wasm_bindgen]
+pub fn my_plain_solution_ex(
+    turn: i32,
+    sparse: &[i32],
+    history: &[i32],
+    rules: &DrawRules,
+    seed: Option<u32>,
+    verbose: bool,
+) -> JsValue {
+
+    let board = match Geometry::default().from_sparse(sparse, turn) {
+        Some(board) => board,
+        None => return JsValue::NULL,
     };
+    let history = GameHistory::from_sparse(history, Geometry::default(), turn, *rules);
 
-    // alert(&format!("{:?}", board));
+    let seed = seed.map(u64::from);
+    match search(&board, &Standard::default(), &history, MAX_NODE, seed, verbose) {
+        Some(result) => JsValue::from_serde(&result).unwrap(),
+        None => JsValue::NULL,
+    }
+}
 
-    let mut hit_cnt: i32 = 0;
-    let mut expand_term_cnt: i32 = 0;
-
-    let mut total_simulate_depth: i32 = 0;
-    let mut max_simulate_depth: i32 = 0;
-    let mut min_simulate_depth: i32 = MAX_STEP as i32;
-
-    let mut total_expand_depth: i32 = 0;
-    let mut max_expand_depth: i32 = 0;
-    let mut min_expand_depth: i32 = MAX_STEP as i32;
-
-    let all = MAX_NODE;
-    for _i in 0..all {
-
-        let MCTSSearchPass {
-            hit,
-            win,
-            expand_term,
-            simulate_depth,
-            expand_depth,
-        } = mcts_search_pass(&mut root, &board, &mut rng, false);
-        if hit {
-            hit_cnt += 1;
-        }
-        if expand_term {
-            expand_term_cnt += 1;
-        }
+pub fn search_tree<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    iterations: usize,
+    seed: Option<u64>,
+) -> (SearchNode, SearchStats) {
 
-        total_expand_depth += expand_depth;
-        max_expand_depth = max_expand_depth.max(expand_depth);
-        min_expand_depth = min_expand_depth.min(expand_depth);
+    // alert(&format!("{:?}", board));
 
-        total_simulate_depth += simulate_depth;
-        max_simulate_depth = max_simulate_depth.max(simulate_depth);
-        min_simulate_depth = min_simulate_depth.min(simulate_depth);
+    let mut root = SearchNode::root(board, rules, history);
+    let mut rng = RngProvider::with_seed(seed);
+    let mut stats = SearchStats::default();
+    // a move that wins at once is played without searching
+    let iterations = if puzzle::forced_win(board, rules, 1) { 0 } else { iterations };
+    for _i in 0..iterations {
+        stats.add(&mcts_search_pass(&mut root, board, rules, history, &mut rng, false));
     }
 
     // if let Ok(value) = JsValue::from_serde(&root) {
@@ -1067,35 +955,23 @@ pub fn my_plain_solution(turn: i32, sparse: &[i32]) -> Move {
     //     log("failed to serilize");
     // }
 
-    if let Some((.., a, b)) = root.data {
-        log(&format!("{} estimated win rate", a / b));
-    }
+    (root, stats)
+}
 
-    log(&format!("{} hits of {}", hit_cnt, all));
-    log(&format!("{} term expansion of {}", expand_term_cnt, all));
-
-    log(&format!(
-        "{} average expand depth",
-        total_expand_depth as f32 / all as f32
-    ));
-    log(&format!("{} max expand depth", max_expand_depth));
-    log(&format!("{} min expand depth", min_expand_depth));
-
-    log(&format!(
-        "{} average simulate depth",
-        total_simulate_depth as f32 / all as f32
-    ));
-    log(&format!("{} max simulate depth", max_simulate_depth));
-    log(&format!("{} min simulate depth", min_simulate_depth));
-
-    unsafe {
-        if let Some(ptr) = root.find_max(true) {
-            let (src, dst) = (*ptr).curr_move;
-            let (x0, y0) = src.to_coord_2d();
-            let (x1, y1) = dst.to_coord_2d();
-            return Move { x0, y0, x1, y1 };
-        } else {
-            panic!()
-        }
-    }
+pub fn search<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    iterations: usize,
+    seed: Option<u64>,
+    verbose: bool,
+) -> Option<SearchResult> {
+
+    let start = now_ms();
+    let (mut root, stats) = search_tree(board, rules, history, iterations, seed);
+    let result = SearchResult::new(&mut root, stats, now_ms() - start)?;
+    if verbose {
+        result.log();
+    }
+    Some(result)
 }
@@ -0,0 +1,208 @@
+use super::*;
+
+// squares are named like "b1": files a, b, ... from left to right, ranks 1, 2,
+// ... from the bottom up, so (0, 0) in the top left corner is "a8" on the
+// standard board
+
+pub fn square_name(geo: Geometry, pos: i32) -> String {
+    let (x, y) = pos.to_coord_2d();
+    format!("{}{}", (b'a' + x as u8) as char, geo.size() - y)
+}
+
+pub fn parse_square(geo: Geometry, name: &str) -> Option<i32> {
+    let bytes = name.as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+    let x = bytes[0].to_ascii_lowercase() as i32 - b'a' as i32;
+    let y = geo.size() - (bytes[1] as i32 - b'0' as i32);
+    if geo.contains(x, y) {
+        Some((x, y).to_coord())
+    } else {
+        None
+    }
+}
+
+// "b1-d3", or "b1xd3" when capturing on `board` (relative to the mover)
+pub fn move_name(board: &[u64; 2], geo: Geometry, src: i32, dst: i32) -> String {
+    let sep = if (board[1] & dst.to_piece()) != 0 { 'x' } else { '-' };
+    format!("{}{}{}", square_name(geo, src), sep, square_name(geo, dst))
+}
+
+pub fn parse_move(geo: Geometry, name: &str) -> Option<(i32, i32)> {
+    let name = name.trim();
+    if name.len() != 5 || !name.is_char_boundary(2) || !name.is_char_boundary(3) {
+        return None;
+    }
+    match &name[2..3] {
+        "-" | "x" | "X" => Some((parse_square(geo, &name[..2])?, parse_square(geo, &name[3..])?)),
+        _ => None,
+    }
+}
+
+// a whitespace separated list of moves on the board of `rules`, move
+// numbers like "12." are skipped
+pub fn parse_record<R: Rules>(rules: &R, text: &str) -> Result<Vec<(i32, i32)>, String> {
+    let geo = rules.geometry();
+    text.split_whitespace()
+        .filter(|token| !token.ends_with('.'))
+        .map(|token| parse_move(geo, token).ok_or_else(|| format!("bad move `{}`", token)))
+        .collect()
+}
+
+// the inverse of `parse_record`, `board` is the start position relative to
+// the first mover; the moves are replayed under `rules`, which decide the
+// captures
+pub fn record_name<R: Rules>(board: &[u64; 2], rules: &R, moves: &[(i32, i32)]) -> String {
+    let geo = rules.geometry();
+    let mut board = *board;
+    let mut names = vec![];
+    for (i, &(src, dst)) in moves.iter().enumerate() {
+        if i % 2 == 0 {
+            names.push(format!("{}.", i / 2 + 1));
+        }
+        names.push(move_name(&board, geo, src, dst));
+        board = rules.apply_move(&board, src, dst);
+    }
+    names.join(" ")
+}
+
+// rows from the top rank down, separated by '/', with 'b' and 'w' for the
+// pieces and digits for runs of empty squares, then the side to move, like
+// "1bbbbbb1/w6w/w6w/w6w/w6w/w6w/w6w/1bbbbbb1 b"
+pub fn position_name(board: &[u64; 2], geo: Geometry, turn: i32) -> String {
+    let black = board[turn as usize];
+    let white = board[1 - turn as usize];
+    let mut rows = vec![];
+    for y in 0..geo.size() {
+        let mut row = String::new();
+        let mut empty = 0;
+        for x in 0..geo.size() {
+            let piece = (x, y).to_coord().to_piece();
+            let name = if (black & piece) != 0 {
+                'b'
+            } else if (white & piece) != 0 {
+                'w'
+            } else {
+                empty += 1;
+                continue;
+            };
+            if empty != 0 {
+                row.push_str(&empty.to_string());
+                empty = 0;
+            }
+            row.push(name);
+        }
+        if empty != 0 {
+            row.push_str(&empty.to_string());
+        }
+        rows.push(row);
+    }
+    format!("{} {}", rows.join("/"), if turn == 0 { 'b' } else { 'w' })
+}
+
+// the inverse of `position_name`, the side to move may be left out for
+// black; gives the geometry, the board relative to the side to move and it
+pub fn parse_position(text: &str) -> Result<(Geometry, [u64; 2], i32), String> {
+    let mut tokens = text.split_whitespace();
+    let rows: Vec<&str> = tokens.next().ok_or("empty position")?.split('/').collect();
+    let geo = Geometry::new(rows.len() as i32)
+        .ok_or_else(|| format!("unsupported board size {}", rows.len()))?;
+    let turn = match tokens.next() {
+        None | Some("b") => 0,
+        Some("w") => 1,
+        Some(token) => return Err(format!("bad side to move `{}`", token)),
+    };
+    if let Some(token) = tokens.next() {
+        return Err(format!("unexpected `{}`", token));
+    }
+
+    // black, white
+    let mut board = [0u64; 2];
+    for (y, row) in rows.iter().enumerate() {
+        let mut x = 0;
+        for c in row.chars() {
+            match c {
+                'b' | 'w' if x < geo.size() => {
+                    let id = if c == 'b' { 0 } else { 1 };
+                    board[id] |= (x, y as i32).to_coord().to_piece();
+                    x += 1;
+                }
+                '1'..='8' => x += c.to_digit(10).unwrap() as i32,
+                'b' | 'w' => x += 1,
+                _ => return Err(format!("bad character `{}` in row {}", c, y + 1)),
+            }
+        }
+        if x != geo.size() {
+            return Err(format!("row {} has {} squares, not {}", y + 1, x, geo.size()));
+        }
+    }
+    if turn == 0 {
+        Ok((geo, board, turn))
+    } else {
+        Ok((geo, [board[1], board[0]], turn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use setup::Setup;
+
+    #[test]
+    fn positions_round_trip() {
+        let setups = [
+            Setup::Standard,
+            Setup::ScrambledEggs,
+            Setup::RandomSymmetric,
+            Setup::Corners,
+        ];
+        for size in 6..=8 {
+            let geo = Geometry::new(size).unwrap();
+            for &setup in setups.iter() {
+                let board = setup.board(geo, &mut RngProvider::from_seed(size as u64));
+                for turn in 0..2 {
+                    let name = position_name(&board, geo, turn);
+                    assert_eq!(parse_position(&name), Ok((geo, board, turn)), "{}", name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        let rules = Standard::default();
+        let geo = rules.geometry();
+        let start = Setup::Standard.board(geo, &mut RngProvider::from_seed(0));
+        // a white piece in reach, so that some moves capture
+        let board = [start[0], start[1] | (2, 2).to_coord().to_piece()];
+        let moves = rules.moves(&board);
+        let mut captures = 0;
+        for &(src, dst) in moves.iter() {
+            let name = move_name(&board, geo, src, dst);
+            if name.contains('x') {
+                captures += 1;
+            }
+            assert_eq!(parse_move(geo, &name), Some((src, dst)), "{}", name);
+        }
+        assert!(captures > 0);
+    }
+
+    #[test]
+    fn records_follow_the_rules() {
+        let geo = Geometry::default();
+        let rules = BlackHole { geo };
+        let pieces = |squares: &[(i32, i32)]| {
+            squares.iter().fold(0, |mask, &sq| mask | sq.to_coord().to_piece())
+        };
+        let board = [pieces(&[(3, 0), (3, 1)]), pieces(&[(0, 3), (6, 3), (7, 3)])];
+        // black falls into the hole, so white lands on an empty square
+        let moves = [((3, 1).to_coord(), rules.hole()), ((0, 3).to_coord(), rules.hole())];
+        let record = record_name(&board, &rules, &moves);
+        assert_eq!(record, "1. d7-d5 a5-d5");
+        assert_eq!(parse_record(&rules, &record), Ok(moves.to_vec()));
+        // where standard rules would have white capture
+        assert_eq!(record_name(&board, &Standard { geo }, &moves), "1. d7-d5 a5xd5");
+    }
+}
@@ -0,0 +1,377 @@
+use super::*;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// visits (all lost) added along a path while its playouts are running
+const VIRTUAL_LOSS: f32 = 1f32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParallelMode {
+    // independent trees, root statistics merged at the end
+    Root,
+    // one shared tree, virtual loss keeps the threads apart
+    Tree,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ParallelConfig {
+    pub mode: ParallelMode,
+    pub threads: usize,
+    // thread `i` plays out with `seed + i`
+    pub seed: u64,
+}
+
+impl Default for ParallelConfig {
+    fn default() -> Self {
+        ParallelConfig {
+            mode: ParallelMode::Root,
+            threads: 4,
+            seed: 0,
+        }
+    }
+}
+
+// `iterations` are split evenly between the threads
+pub fn parallel_search<R: Rules + Copy + Send + 'static>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    iterations: usize,
+    config: ParallelConfig,
+) -> Option<SearchResult> {
+    let start = now_ms();
+    let threads = config.threads.max(1);
+    let per_thread = (iterations + threads - 1) / threads;
+
+    let seed = config.seed;
+    let (mut root, stats) = match config.mode {
+        ParallelMode::Root => root_parallel(board, rules, history, per_thread, threads, seed),
+        ParallelMode::Tree => tree_parallel(board, rules, history, per_thread, threads, seed),
+    };
+
+    SearchResult::new(&mut root, stats, now_ms() - start)
+}
+
+fn root_parallel<R: Rules + Copy + Send + 'static>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    iterations: usize,
+    threads: usize,
+    seed: u64,
+) -> (SearchNode, SearchStats) {
+    let workers: Vec<_> = (0..threads)
+        .map(|i| {
+            let (board, rules) = (*board, *rules);
+            let history = history.clone();
+            thread::spawn(move || {
+                let seed = Some(seed + i as u64);
+                search_tree(&board, &rules, &history, iterations, seed)
+            })
+        })
+        .collect();
+
+    let mut trees = workers.into_iter().map(|worker| worker.join().unwrap());
+    let (mut root, mut stats) = trees.next().unwrap();
+    for (other, other_stats) in trees {
+        merge_root(&mut root, other);
+        stats.merge(&other_stats);
+    }
+    (root, stats)
+}
+
+// adds the root statistics of `other` to `root`
+fn merge_root(root: &mut SearchNode, other: SearchNode) {
+    let (data, a, b) = match other.data {
+        Some(data) => data,
+        None => return,
+    };
+    let (ref mut data0, ref mut a0, ref mut b0) = root.data.as_mut().unwrap();
+    *a0 += a;
+    *b0 += b;
+
+    // both trees generated the root moves in the same order
+    if let (SearchNodeData::Mid { ref mut childs, .. }, SearchNodeData::Mid { childs: others, .. }) =
+        (data0, data)
+    {
+        for (child, other) in childs.iter_mut().zip(others.into_iter()) {
+            match (&mut child.data, other.data) {
+                (_, None) => {}
+                (None, data) => child.data = data,
+                (Some((ref mut data, ref mut a, ref mut b)), Some((other, a1, b1))) => {
+                    *a += a1;
+                    *b += b1;
+                    // the other tree's node replaces this one only if it is
+                    // proven, a subtree searched here is kept otherwise
+                    match other {
+                        SearchNodeData::Mid { .. } => {}
+                        other => *data = other,
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn tree_parallel<R: Rules + Copy + Send + 'static>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    iterations: usize,
+    threads: usize,
+    seed: u64,
+) -> (SearchNode, SearchStats) {
+    let tree = Arc::new(Mutex::new(SearchNode::root(board, rules, history)));
+
+    let workers: Vec<_> = (0..threads)
+        .map(|i| {
+            let (board, rules) = (*board, *rules);
+            let history = history.clone();
+            let tree = tree.clone();
+            thread::spawn(move || {
+                let mut rng = RngProvider::from_seed(seed + i as u64);
+                let mut stats = SearchStats::default();
+                for _i in 0..iterations {
+                    stats.add(&tree_search_pass(&tree, &board, &rules, &history, &mut rng));
+                }
+                stats
+            })
+        })
+        .collect();
+
+    let mut stats = SearchStats::default();
+    for worker in workers {
+        stats.merge(&worker.join().unwrap());
+    }
+
+    let root = match Arc::try_unwrap(tree) {
+        Ok(tree) => tree.into_inner().unwrap(),
+        Err(_) => panic!(),
+    };
+    (root, stats)
+}
+
+// calls `f` with the depth of every node still reachable along `moves`
+fn for_each_on_path<F: FnMut(usize, &mut SearchNode)>(
+    root: &mut SearchNode,
+    moves: &[(i32, i32)],
+    mut f: F,
+) {
+    let mut node = root;
+    f(0, node);
+    for (depth, mv) in moves.iter().enumerate() {
+        let next = match node.data {
+            Some((SearchNodeData::Mid { ref mut childs, .. }, ..)) => {
+                childs.iter_mut().find(|child| child.curr_move == *mv)
+            }
+            _ => None,
+        };
+        match next {
+            Some(child) if child.data.is_some() => {
+                node = child;
+                f(depth + 1, node);
+            }
+            _ => break,
+        }
+    }
+}
+
+// the nodes along `moves`, if the whole path is still in the tree
+fn path_of<'a>(
+    root: &'a mut SearchNode,
+    moves: &[(i32, i32)],
+) -> Option<Vec<&'a mut SearchNode>> {
+    unsafe {
+        let mut node_ptr: *mut SearchNode = root;
+        let mut path: Vec<&mut SearchNode> = vec![&mut *node_ptr];
+        for mv in moves.iter() {
+            node_ptr = match (*node_ptr).data {
+                Some((SearchNodeData::Mid { ref mut childs, .. }, ..)) => {
+                    childs.iter_mut().find(|child| child.curr_move == *mv)?
+                }
+                _ => return None,
+            };
+            path.push(&mut *node_ptr);
+        }
+        Some(path)
+    }
+}
+
+// a childless copy of `node`, enough to play out from it
+fn leaf_copy(node: &SearchNode) -> SearchNode {
+    let data = match node.data {
+        Some((SearchNodeData::Mid { ref board, .. }, ..)) => SearchNodeData::Mid {
+            curr: 0,
+            full: true,
+            board: *board,
+            childs: vec![],
+        },
+        Some((SearchNodeData::Term(win), ..)) => SearchNodeData::Term(win),
+        _ => SearchNodeData::Draw,
+    };
+    SearchNode {
+        curr_move: node.curr_move,
+        data: Some((data, 0f32, 0f32)),
+    }
+}
+
+// one pass over the shared tree; the playouts run without holding the lock,
+// the path is walked again by its moves to back propagate.
+fn tree_search_pass<R: Rules>(
+    tree: &Mutex<SearchNode>,
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    rng: &mut RngProvider,
+) -> MCTSSearchPass {
+    let mut pass = MCTSSearchPass {
+        hit: false,
+        value: 0f32,
+        expand_term: false,
+        expand_depth: 0,
+        simulate_depths: [0u32; MAX_STEP + 1],
+        undecided: 0,
+    };
+
+    let (moves, leaf, mut draws) = {
+        let mut root = tree.lock().unwrap();
+        let root_rate = root.win_rate();
+        let (moves, leaf, draws) = {
+            let (sel_res, path) = root.select(board, false);
+            pass.expand_depth = path.len() as i32;
+
+            let (curr_node, curr_board) = match sel_res {
+                Some(sel) => sel,
+                None => return pass,
+            };
+
+            let draws = DrawContext::new(history, &path[..path.len() - 1], root_rate);
+            let moves: Vec<(i32, i32)> = path.iter().skip(1).map(|node| node.curr_move).collect();
+
+            pass.expand_term = curr_node.expand(&curr_board, rules, &draws, false);
+            (moves, leaf_copy(curr_node), draws)
+        };
+
+        for_each_on_path(&mut root, &moves, |_depth, node| {
+            let (.., a, b) = node.data.as_mut().unwrap();
+            *a += VIRTUAL_LOSS;
+            *b += VIRTUAL_LOSS;
+        });
+        (moves, leaf, draws)
+    };
+
+    // the same values `mcts_search_pass` would back propagate, summed per depth
+    let len = moves.len() + 1;
+    let mut totals = vec![0f32; len];
+    let mut value = 0f32;
+    for _i in 0..SIMULATE_COUNT {
+        let (simulate_res, simulate_dep) =
+            leaf.simulate(rules, rng, &mut draws, false);
+        pass.simulate_depths[simulate_dep as usize] += 1;
+        if let Some(v) = simulate_res {
+            value = v;
+            pass.hit = true;
+        } else {
+            pass.undecided += 1;
+        }
+        for total in totals.iter_mut().rev() {
+            *total += value;
+            value = 1f32 - value;
+        }
+    }
+
+    let mut root = tree.lock().unwrap();
+    for_each_on_path(&mut root, &moves, |depth, node| {
+        let (.., a, b) = node.data.as_mut().unwrap();
+        *a += totals[depth] - VIRTUAL_LOSS;
+        *b += SIMULATE_COUNT as f32 - VIRTUAL_LOSS;
+    });
+    // last, as in `mcts_search_pass`
+    if let Some((SearchNodeData::Term(win), ..)) = leaf.data {
+        if let Some(mut path) = path_of(&mut root, &moves) {
+            propagate_term(&mut path, win);
+        }
+    }
+    pass.value = 1f32 - value;
+    pass
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start() -> [u64; 2] {
+        setup::Setup::Standard.board(Geometry::default(), &mut RngProvider::from_seed(0))
+    }
+
+    fn summary(result: &SearchResult) -> (Move, f32, Vec<(Move, f32, f32)>) {
+        let children = result
+            .children
+            .iter()
+            .map(|child| (child.mv.clone(), child.visits, child.value))
+            .collect();
+        (result.best.clone(), result.visits, children)
+    }
+
+    #[test]
+    fn root_parallel_is_reproducible() {
+        let history = GameHistory::new(DrawRules::default());
+        let config = ParallelConfig {
+            mode: ParallelMode::Root,
+            threads: 3,
+            seed: 7,
+        };
+        let search = || {
+            parallel_search(&start(), &Standard::default(), &history, 600, config).unwrap()
+        };
+        assert_eq!(summary(&search()), summary(&search()));
+    }
+
+    #[test]
+    fn tree_parallel_proves_the_mate() {
+        let rules = Standard::default();
+        let history = GameHistory::new(DrawRules::default());
+        let pieces = |squares: &[(i32, i32)]| {
+            squares.iter().fold(0, |mask, &sq| mask | sq.to_coord().to_piece())
+        };
+        // the mate in two of the puzzle tests
+        let board = [
+            pieces(&[(3, 1), (0, 2), (0, 3)]),
+            pieces(&[(1, 2), (1, 5), (6, 7), (7, 7)]),
+        ];
+        let (mut root, stats) = tree_parallel(&board, &rules, &history, 400, 2, 3);
+        // no virtual loss is left behind
+        assert_eq!(root.visits(), (stats.iterations * SIMULATE_COUNT) as f32);
+        let result = SearchResult::new(&mut root, stats, 0f64).unwrap();
+        let (src, dst) = ((3, 1).to_coord(), (2, 1).to_coord());
+        assert_eq!(result.best, Move::from_coords(src, dst));
+    }
+
+    // the number of children below every root move
+    fn grandchildren(root: &SearchNode) -> Vec<usize> {
+        let childs_of = |node: &SearchNode| match node.data {
+            Some((SearchNodeData::Mid { ref childs, .. }, ..)) => childs.len(),
+            _ => 0,
+        };
+        match root.data {
+            Some((SearchNodeData::Mid { ref childs, .. }, ..)) => {
+                childs.iter().map(childs_of).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn merge_keeps_searched_subtrees() {
+        let rules = Standard::default();
+        let history = GameHistory::new(DrawRules::default());
+        let (mut root, _) = search_tree(&start(), &rules, &history, 400, Some(1));
+        let (other, _) = search_tree(&start(), &rules, &history, 400, Some(2));
+        let before = grandchildren(&root);
+        let visits = root.visits() + other.visits();
+        merge_root(&mut root, other);
+        assert_eq!(grandchildren(&root), before);
+        assert_eq!(root.visits(), visits);
+    }
+}
@@ -0,0 +1,355 @@
+use super::*;
+
+// proof-number search: proves that one side can force a connection.
+// repetitions and the move limit are not taken into account, the proofs
+// searched for MCTS leaves are kept short enough for the caller to rule
+// them out instead.
+
+const PN_INF: u32 = 1 << 30;
+
+// expansions between two clock reads
+const PN_TIME_CHECK_INTERVAL: usize = 64;
+
+// positions with at most this many pieces on the board are late-game
+const LATE_GAME_PIECES: u32 = 12;
+// budget of the solver for a late-game root, and for a late-game node
+// expanded by MCTS
+const PN_ROOT_NODES: usize = 32768;
+const PN_LEAF_NODES: usize = 256;
+// leaf proofs are cut off after this many plies, see `draw_may_intervene`
+const PN_LEAF_PLIES: u32 = 3;
+// and only tried this close to the root: on 4000 pass searches of random
+// five against five positions this cost within 5% of no leaf proofs, where
+// a depth of 6 cost 30% and no limit 45%
+const PN_LEAF_MAX_DEPTH: i32 = 4;
+// no cut off
+const PN_NO_HORIZON: u32 = !0u32;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PnResult {
+    // for the side to move
+    pub proven: Proven,
+    // the winning move, if proven a win
+    #[serde(rename = "move")]
+    pub best: Option<Move>,
+    pub nodes: usize,
+}
+
+#[derive(Debug)]
+struct PnNode {
+    board: [u64; 2],
+    curr_move: (i32, i32),
+    parent: usize,
+    childs: Vec<usize>,
+    // the side that should be proven to win is to move
+    or: bool,
+    // plies from the root
+    depth: u32,
+    pn: u32,
+    dn: u32,
+}
+
+struct PnTree<'a, R: Rules> {
+    rules: &'a R,
+    nodes: Vec<PnNode>,
+    // nodes this deep are not expanded
+    horizon: u32,
+}
+
+fn sum(a: u32, b: u32) -> u32 {
+    (a + b).min(PN_INF)
+}
+
+pub fn is_late_game(board: &[u64; 2]) -> bool {
+    board[0] != 0 && board[1] != 0 && (board[0] | board[1]).count_ones() <= LATE_GAME_PIECES
+}
+
+impl<'a, R: Rules> PnTree<'a, R> {
+    fn new(board: &[u64; 2], rules: &'a R, or: bool, horizon: u32) -> Self {
+        let mut tree = PnTree {
+            rules,
+            nodes: vec![],
+            horizon,
+        };
+        tree.push(board, EMPTY_MOVE, 0, or);
+        tree
+    }
+
+    fn push(&mut self, board: &[u64; 2], curr_move: (i32, i32), parent: usize, or: bool) {
+        let depth = self.nodes.get(parent).map_or(0, |parent| parent.depth + 1);
+        let (pn, dn) = match self.rules.is_win_state(board) {
+            // the side to move wins
+            Some(win) => {
+                if win == or {
+                    (0, PN_INF)
+                } else {
+                    (PN_INF, 0)
+                }
+            }
+            // not proven within the horizon
+            None if depth >= self.horizon => (PN_INF, 0),
+            None => (1, 1),
+        };
+        self.nodes.push(PnNode {
+            board: *board,
+            curr_move,
+            parent,
+            childs: vec![],
+            or,
+            depth,
+            pn,
+            dn,
+        });
+    }
+
+    fn most_proving(&self) -> usize {
+        let mut id = 0;
+        while !self.nodes[id].childs.is_empty() {
+            let node = &self.nodes[id];
+            id = *node
+                .childs
+                .iter()
+                .min_by_key(|&&child| {
+                    if node.or {
+                        self.nodes[child].pn
+                    } else {
+                        self.nodes[child].dn
+                    }
+                })
+                .unwrap();
+        }
+        id
+    }
+
+    fn expand(&mut self, id: usize) {
+        let board = self.nodes[id].board;
+        let or = self.nodes[id].or;
+        for (src, dst) in self.rules.moves(&board) {
+            let child = self.nodes.len();
+            let next = self.rules.apply_move(&board, src, dst);
+            self.push(&next, (src, dst), id, !or);
+            self.nodes[id].childs.push(child);
+        }
+        if self.nodes[id].childs.is_empty() {
+            // passing is not modelled, nothing can be proven from here
+            self.nodes[id].pn = PN_INF;
+            self.nodes[id].dn = 0;
+        }
+    }
+
+    fn update(&mut self, mut id: usize) {
+        loop {
+            let node = &self.nodes[id];
+            if !node.childs.is_empty() {
+                let (pn, dn) = node.childs.iter().fold(
+                    if node.or { (PN_INF, 0) } else { (0, PN_INF) },
+                    |(pn, dn), &child| {
+                        let child = &self.nodes[child];
+                        if node.or {
+                            (pn.min(child.pn), sum(dn, child.dn))
+                        } else {
+                            (sum(pn, child.pn), dn.min(child.dn))
+                        }
+                    },
+                );
+                let node = &mut self.nodes[id];
+                node.pn = pn;
+                node.dn = dn;
+            }
+            if id == 0 {
+                break;
+            }
+            id = self.nodes[id].parent;
+        }
+    }
+
+    // Some(true) if proven, Some(false) if disproven
+    fn solve(&mut self, node_limit: usize, deadline: Option<f64>) -> Option<bool> {
+        let mut expansions = 0;
+        while self.nodes[0].pn != 0 && self.nodes[0].dn != 0 && self.nodes.len() < node_limit {
+            let id = self.most_proving();
+            self.expand(id);
+            self.update(id);
+
+            expansions += 1;
+            if expansions % PN_TIME_CHECK_INTERVAL == 0 {
+                if let Some(deadline) = deadline {
+                    if now_ms() >= deadline {
+                        break;
+                    }
+                }
+            }
+        }
+        match (self.nodes[0].pn, self.nodes[0].dn) {
+            (0, _) => Some(true),
+            (_, 0) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+// solves `board` for the side to move within about `node_limit` nodes and
+// `ms` milliseconds (0 for no time limit)
+pub fn pn_search<R: Rules>(board: &[u64; 2], rules: &R, node_limit: usize, ms: f64) -> PnResult {
+    pn_search_within(board, rules, node_limit, ms, PN_NO_HORIZON)
+}
+
+// `pn_search` for wins and losses within `horizon` plies
+fn pn_search_within<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    node_limit: usize,
+    ms: f64,
+    horizon: u32,
+) -> PnResult {
+    let deadline = if ms > 0f64 { Some(now_ms() + ms) } else { None };
+
+    // first try to prove a win for the side to move
+    let mut tree = PnTree::new(board, rules, true, horizon);
+    let res = tree.solve(node_limit, deadline);
+    let mut nodes = tree.nodes.len();
+    if res == Some(true) {
+        let best = tree.nodes[0]
+            .childs
+            .iter()
+            .map(|&child| &tree.nodes[child])
+            .find(|child| child.pn == 0)
+            .map(|child| Move::from_coords(child.curr_move.0, child.curr_move.1));
+        return PnResult {
+            proven: Proven::Win,
+            best,
+            nodes,
+        };
+    }
+
+    // then, with what is left, a win for the opponent
+    let mut proven = Proven::Unknown;
+    if res == Some(false) && nodes < node_limit {
+        let mut tree = PnTree::new(board, rules, false, horizon);
+        if tree.solve(node_limit - nodes, deadline) == Some(true) {
+            proven = Proven::Loss;
+        }
+        nodes += tree.nodes.len();
+    }
+    PnResult {
+        proven,
+        best: None,
+        nodes,
+    }
+}
+
+// whether the draw rules could end a line of `plies` plies, starting `ply`
+// plies below the root, before a proof along it does: the line could reach
+// the move limit, or come back to a position on `stack` that is one visit
+// short of a repetition. A line also needs four plies to visit a position a
+// second time, which leaf proofs are too short for.
+fn draw_may_intervene(history: &GameHistory, stack: &[u64], ply: i32, plies: u32) -> bool {
+    let DrawRules {
+        repetition,
+        move_limit,
+        ..
+    } = history.rules;
+    let end = history.moves_played.saturating_add(ply as u32).saturating_add(plies);
+    (move_limit != 0 && end >= move_limit)
+        || (repetition != 0
+            && stack.iter().any(|&hash| {
+                stack.iter().filter(|&&h| h == hash).count() as u32 + 1 >= repetition
+            }))
+}
+
+impl SearchNode {
+    // marks a root move proven to win by the solver, so MCTS will keep to it;
+    // not under a move limit, root proofs can be of any length
+    pub fn solve_root<R: Rules>(&mut self, board: &[u64; 2], rules: &R, history: &GameHistory) {
+        if !is_late_game(board) || draw_may_intervene(history, &history.hashes, 0, PN_NO_HORIZON) {
+            return;
+        }
+        let best = match pn_search(board, rules, PN_ROOT_NODES, 0f64) {
+            PnResult {
+                proven: Proven::Win,
+                best: Some(best),
+                ..
+            } => ((best.x0, best.y0).to_coord(), (best.x1, best.y1).to_coord()),
+            _ => return,
+        };
+        if let Some((SearchNodeData::Mid { ref mut childs, .. }, ..)) = self.data {
+            for child in childs.iter_mut().filter(|child| child.curr_move == best) {
+                // lost for the side to move after it
+                child.data = Some((SearchNodeData::Term(false), 0f32, 1f32));
+            }
+        }
+    }
+}
+
+impl SearchNodeData {
+    // a proven node for a late-game `board` the solver can settle quickly,
+    // `ply` plies below the root with the positions before it on `stack`;
+    // only tried where the draw rules cannot cut the proof short
+    pub fn solved<R: Rules>(
+        board: &[u64; 2],
+        rules: &R,
+        history: &GameHistory,
+        stack: &[u64],
+        ply: i32,
+    ) -> Option<Self> {
+        if ply > PN_LEAF_MAX_DEPTH
+            || !is_late_game(board)
+            || draw_may_intervene(history, stack, ply, PN_LEAF_PLIES)
+        {
+            return None;
+        }
+        match pn_search_within(board, rules, PN_LEAF_NODES, 0f64, PN_LEAF_PLIES).proven {
+            Proven::Win => Some(SearchNodeData::Term(true)),
+            Proven::Loss => Some(SearchNodeData::Term(false)),
+            _ => None,
+        }
+    }
+}
+
+// `{ proven, move, nodes }` for `turn` to move on `sparse`
+#[wasm_bindgen]
+pub fn solve_position(turn: i32, sparse: &[i32], node_limit: usize, ms: f64) -> JsValue {
+    let rules = Standard::default();
+    match rules.geometry().from_sparse(sparse, turn) {
+        Some(board) => JsValue::from_serde(&pn_search(&board, &rules, node_limit, ms)).unwrap(),
+        None => JsValue::NULL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the side to move connects with its next move
+    const WIN_IN_ONE: [u64; 2] = [1u64 | 1 << 1 | 1 << 11, 1u64 << 63 | 1 << 47];
+
+    #[test]
+    fn leaves_are_solved() {
+        let history = GameHistory::new(DrawRules::default());
+        let solved = SearchNodeData::solved(&WIN_IN_ONE, &Standard::default(), &history, &[], 1);
+        assert!(match solved {
+            Some(SearchNodeData::Term(true)) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn no_proof_where_a_draw_could_come_first() {
+        let rules = Standard::default();
+        let history = GameHistory::new(DrawRules::default());
+        let solved = |history: &GameHistory, stack: &[u64], ply| {
+            SearchNodeData::solved(&WIN_IN_ONE, &rules, history, stack, ply).is_some()
+        };
+        // too deep in the tree
+        assert!(!solved(&history, &[], PN_LEAF_MAX_DEPTH + 1));
+        // a position seen twice, one more visit draws
+        assert!(solved(&history, &[1, 2], 1));
+        assert!(!solved(&history, &[1, 2, 1], 1));
+        // the move limit within reach of the proof
+        let limited = GameHistory::new(DrawRules::new(3, 40, 0.5f32));
+        assert!(solved(&limited, &[], 1));
+        let mut history = limited.clone();
+        history.moves_played = 37;
+        assert!(!solved(&history, &[], 1));
+    }
+}
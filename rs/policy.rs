@@ -0,0 +1,272 @@
+use super::*;
+
+// how the move to play is picked from the root children once the search is
+// over; a move proven to win is played whatever the policy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FinalPolicy {
+    // the highest value, however few visits it has
+    MaxValue,
+    // the most visits, the "robust child"
+    MaxVisits,
+    // the highest value among the children with at least `min_visits`
+    // visits, the "secure child"; the most visited if none has
+    Secure { min_visits: f32 },
+    // at random, with odds of visits ^ (1 / temperature)
+    Proportional { temperature: f32 },
+}
+
+impl FinalPolicy {
+    // `param` is the minimum visits for "secure" and the temperature for
+    // "proportional", unused otherwise
+    pub fn from_name(name: &str, param: f32) -> Option<FinalPolicy> {
+        match name.to_ascii_lowercase().as_str() {
+            "max-value" => Some(FinalPolicy::MaxValue),
+            "max-visits" | "robust" => Some(FinalPolicy::MaxVisits),
+            "secure" => Some(FinalPolicy::Secure { min_visits: param }),
+            "proportional" if param > 0f32 => {
+                Some(FinalPolicy::Proportional { temperature: param })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FinalPolicy::MaxValue => "max-value",
+            FinalPolicy::MaxVisits => "max-visits",
+            FinalPolicy::Secure { .. } => "secure",
+            FinalPolicy::Proportional { .. } => "proportional",
+        }
+    }
+
+    // the `param` of `from_name`
+    pub fn param(&self) -> f32 {
+        match *self {
+            FinalPolicy::Secure { min_visits } => min_visits,
+            FinalPolicy::Proportional { temperature } => temperature,
+            _ => 0f32,
+        }
+    }
+}
+
+impl Default for FinalPolicy {
+    fn default() -> Self {
+        FinalPolicy::MaxValue
+    }
+}
+
+// the first of `childs` with the highest `key`
+fn max_by<F: Fn(&SearchNode) -> f32>(childs: &[&SearchNode], key: F) -> Option<(i32, i32)> {
+    childs
+        .iter()
+        .fold(None, |best: Option<(&SearchNode, f32)>, &child| {
+            let fact = key(child);
+            match best {
+                Some((_, max)) if max >= fact => best,
+                _ => Some((child, fact)),
+            }
+        })
+        .map(|(child, _)| child.curr_move)
+}
+
+fn value(child: &SearchNode) -> f32 {
+    child.data.as_ref().map_or(0f32, |(.., a, b)| 1f32 - a / b)
+}
+
+impl SearchNode {
+    // the root move `policy` plays, None if no child has been visited;
+    // moves proven to lose are left out unless there is nothing else
+    pub fn final_move(&self, policy: FinalPolicy, rng: &mut RngProvider) -> Option<(i32, i32)> {
+        let childs: Vec<&SearchNode> = match self.data {
+            Some((SearchNodeData::Mid { ref childs, .. }, ..)) => {
+                childs.iter().filter(|child| child.data.is_some()).collect()
+            }
+            _ => return None,
+        };
+        if let Some(win) = childs.iter().find(|child| child.proven() == Proven::Win) {
+            return Some(win.curr_move);
+        }
+        let alive: Vec<&SearchNode> = childs
+            .iter()
+            .cloned()
+            .filter(|child| child.proven() != Proven::Loss)
+            .collect();
+        let childs = if alive.is_empty() { childs } else { alive };
+
+        match policy {
+            FinalPolicy::MaxValue => max_by(&childs, value),
+            FinalPolicy::MaxVisits => max_by(&childs, SearchNode::visits),
+            FinalPolicy::Secure { min_visits } => {
+                let secure: Vec<&SearchNode> = childs
+                    .iter()
+                    .cloned()
+                    .filter(|child| child.visits() >= min_visits)
+                    .collect();
+                if secure.is_empty() {
+                    max_by(&childs, SearchNode::visits)
+                } else {
+                    max_by(&secure, value)
+                }
+            }
+            FinalPolicy::Proportional { temperature } => {
+                // relative to the most visited move, to keep clear of overflow
+                let most = childs.iter().fold(0f32, |most, child| most.max(child.visits()));
+                let weights: Vec<f32> = childs
+                    .iter()
+                    .map(|child| (child.visits() / most).powf(1f32 / temperature))
+                    .collect();
+                let total: f32 = weights.iter().sum();
+                let mut rn = rng.below(1 << 16) as f32 / 65536f32 * total;
+                for (child, weight) in childs.iter().zip(weights.iter()) {
+                    if rn < *weight {
+                        return Some(child.curr_move);
+                    }
+                    rn -= weight;
+                }
+                max_by(&childs, SearchNode::visits)
+            }
+        }
+    }
+
+    // whether the highest value and the most visits fall on the same move
+    pub fn policies_agree(&self) -> bool {
+        let mut rng = RngProvider::from_seed(0);
+        let by_value = self.final_move(FinalPolicy::MaxValue, &mut rng);
+        by_value.is_some() && by_value == self.final_move(FinalPolicy::MaxVisits, &mut rng)
+    }
+}
+
+impl SearchResult {
+    // `SearchResult::new` with `best` picked by `policy`
+    pub fn with_policy(
+        root: &mut SearchNode,
+        stats: SearchStats,
+        elapsed_ms: f64,
+        policy: FinalPolicy,
+        rng: &mut RngProvider,
+    ) -> Option<Self> {
+        let mut result = SearchResult::new(root, stats, elapsed_ms)?;
+        if let Some((src, dst)) = root.final_move(policy, rng) {
+            result.best = Move::from_coords(src, dst);
+        }
+        Some(result)
+    }
+}
+
+// `search` with the move picked by `policy`; after `iterations` it goes on
+// for up to `extra` passes until the highest value and the most visits agree
+pub fn search_with_policy<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    iterations: usize,
+    extra: usize,
+    policy: FinalPolicy,
+    seed: Option<u64>,
+) -> Option<SearchResult> {
+    let start = now_ms();
+    let mut root = SearchNode::root(board, rules, history);
+    let mut rng = RngProvider::with_seed(seed);
+    let mut stats = SearchStats::default();
+    let budget = if puzzle::forced_win(board, rules, 1) { 0 } else { iterations + extra };
+    for i in 0..budget {
+        if i >= iterations && root.policies_agree() {
+            break;
+        }
+        stats.add(&mcts_search_pass(&mut root, board, rules, history, &mut rng, false));
+    }
+    SearchResult::with_policy(&mut root, stats, now_ms() - start, policy, &mut rng)
+}
+
+// `my_plain_solution` with the move picked by a named policy, see
+// `FinalPolicy::from_name`, and up to `extra` passes more until the highest
+// value and the most visits agree; None if the name is unknown
+#[wasm_bindgen]
+pub fn my_plain_solution_policy(
+    turn: i32,
+    sparse: &[i32],
+    policy: &str,
+    param: f32,
+    extra: u32,
+    seed: Option<u32>,
+) -> Option<Move> {
+    let policy = FinalPolicy::from_name(policy, param)?;
+    let rules = Standard::default();
+    let board = rules.geometry().from_sparse(sparse, turn)?;
+    let history = GameHistory::new(DrawRules::default());
+    let seed = seed.map(u64::from);
+    if let Some((src, dst)) = book::book_move(&board, &mut RngProvider::with_seed(seed)) {
+        return Some(Move::from_coords(src, dst));
+    }
+    let result =
+        search_with_policy(&board, &rules, &history, MAX_NODE, extra as usize, policy, seed)?;
+    Some(result.best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `visits` and the mean value for the side to move at the root
+    fn child(x1: i32, visits: f32, value: f32) -> SearchNode {
+        SearchNode {
+            curr_move: ((0, 0).to_coord(), (x1, 0).to_coord()),
+            data: Some((SearchNodeData::Draw, (1f32 - value) * visits, visits)),
+        }
+    }
+
+    fn root(childs: Vec<SearchNode>) -> SearchNode {
+        let visits = childs.iter().map(SearchNode::visits).sum();
+        SearchNode {
+            curr_move: EMPTY_MOVE,
+            data: Some((
+                SearchNodeData::Mid {
+                    curr: 0,
+                    full: true,
+                    board: [0, 0],
+                    childs,
+                },
+                visits / 2f32,
+                visits,
+            )),
+        }
+    }
+
+    fn dst(mv: Option<(i32, i32)>) -> i32 {
+        mv.unwrap().1.to_coord_2d().0
+    }
+
+    #[test]
+    fn policies_pick_different_moves() {
+        // the best value on few visits, the most visits, and a good value on
+        // enough visits
+        let root = root(vec![
+            child(1, 2f32, 1f32),
+            child(2, 100f32, 0.55f32),
+            child(3, 60f32, 0.6f32),
+        ]);
+        let mut rng = RngProvider::from_seed(3);
+        assert_eq!(dst(root.final_move(FinalPolicy::MaxValue, &mut rng)), 1);
+        assert_eq!(dst(root.final_move(FinalPolicy::MaxVisits, &mut rng)), 2);
+        let secure = FinalPolicy::Secure { min_visits: 50f32 };
+        assert_eq!(dst(root.final_move(secure, &mut rng)), 3);
+        let too_few = FinalPolicy::Secure { min_visits: 1000f32 };
+        assert_eq!(dst(root.final_move(too_few, &mut rng)), 2);
+
+        // at temperature 1 in proportion to the visits
+        let mut counts = [0; 4];
+        let proportional = FinalPolicy::Proportional { temperature: 1f32 };
+        for _i in 0..1000 {
+            counts[dst(root.final_move(proportional, &mut rng)) as usize] += 1;
+        }
+        assert!(counts[2] > counts[3] && counts[3] > counts[1] && counts[1] > 0);
+        assert!(!root.policies_agree());
+    }
+
+    #[test]
+    fn policies_agree_on_the_robust_best() {
+        let root = root(vec![child(1, 20f32, 0.4f32), child(2, 100f32, 0.55f32)]);
+        assert!(root.policies_agree());
+        assert!(!self::root(vec![SearchNode { curr_move: (0, 1), data: None }]).policies_agree());
+    }
+}
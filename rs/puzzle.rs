@@ -0,0 +1,251 @@
+use super::*;
+
+use std::collections::HashSet;
+
+use notation::{position_name, record_name};
+use query::legal_moves_of;
+use rng::splitmix64;
+
+// the solver is exhaustive and ignores draws, every extra ply costs about
+// the number of legal moves (~30), so keep `plies` small
+
+// self-play games are cut off after this many plies
+const MAX_GAME_PLIES: u32 = 200;
+// faster wins make poor puzzles
+const MIN_PUZZLE_PLIES: u32 = 3;
+
+// whether the side to move can connect within `plies` plies
+pub(crate) fn forced_win<R: Rules>(board: &[u64; 2], rules: &R, plies: u32) -> bool {
+    plies > 0
+        && legal_moves_of(board, rules)
+            .into_iter()
+            .any(|(src, dst)| wins_by(board, rules, src, dst, plies))
+}
+
+// whether the side to move loses within `plies` plies whatever it plays
+fn forced_loss<R: Rules>(board: &[u64; 2], rules: &R, plies: u32) -> bool {
+    let moves = legal_moves_of(board, rules);
+    // passing is not modelled
+    !moves.is_empty()
+        && moves.into_iter().all(|(src, dst)| {
+            let next = rules.apply_move(board, src, dst);
+            match rules.is_win_state(&next) {
+                Some(win) => win,
+                None => forced_win(&next, rules, plies - 1),
+            }
+        })
+}
+
+// whether playing (src, dst) wins within `plies` plies
+pub(crate) fn wins_by<R: Rules>(board: &[u64; 2], rules: &R, src: i32, dst: i32, plies: u32) -> bool {
+    let next = rules.apply_move(board, src, dst);
+    match rules.is_win_state(&next) {
+        // the opponent is to move
+        Some(win) => !win,
+        None => plies >= 3 && forced_loss(&next, rules, plies - 1),
+    }
+}
+
+// every move that wins within `plies` plies
+pub fn winning_moves_of<R: Rules>(board: &[u64; 2], rules: &R, plies: u32) -> Vec<(i32, i32)> {
+    legal_moves_of(board, rules)
+        .into_iter()
+        .filter(|&(src, dst)| wins_by(board, rules, src, dst, plies))
+        .collect()
+}
+
+// the fewest plies, up to `max_plies`, the side to move needs to win, and
+// every move that does it
+pub fn shortest_win<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    max_plies: u32,
+) -> Option<(u32, Vec<(i32, i32)>)> {
+    (1..max_plies + 1)
+        .step_by(2)
+        .map(|plies| (plies, winning_moves_of(board, rules, plies)))
+        .find(|(_plies, moves)| !moves.is_empty())
+}
+
+// a line of a win in `plies` plies: the winner keeps to the fastest win, the
+// loser holds out the longest
+fn main_line<R: Rules>(board: &[u64; 2], rules: &R, plies: u32) -> Vec<(i32, i32)> {
+    let mut line = vec![];
+    let mut board = *board;
+    let mut plies = plies;
+    while let Some((n, moves)) = shortest_win(&board, rules, plies) {
+        let (src, dst) = moves[0];
+        line.push((src, dst));
+        board = rules.apply_move(&board, src, dst);
+        if rules.is_win_state(&board).is_some() {
+            break;
+        }
+
+        let curr = board;
+        let reply = legal_moves_of(&curr, rules).into_iter().max_by_key(|&(src, dst)| {
+            let next = rules.apply_move(&curr, src, dst);
+            if rules.is_win_state(&next).is_some() {
+                0
+            } else {
+                shortest_win(&next, rules, n - 2).map_or(n, |(m, _moves)| m)
+            }
+        });
+        match reply {
+            Some((src, dst)) => {
+                line.push((src, dst));
+                board = rules.apply_move(&board, src, dst);
+            }
+            None => break,
+        }
+        if rules.is_win_state(&board).is_some() {
+            break;
+        }
+        plies = n - 2;
+    }
+    line
+}
+
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    // relative to the side to move
+    pub board: [u64; 2],
+    pub geo: Geometry,
+    pub turn: i32,
+    pub plies: u32,
+    pub solution: Vec<(i32, i32)>,
+}
+
+impl Puzzle {
+    // a puzzle if the fastest win for the side to move, within `max_plies`,
+    // starts with a single move
+    pub fn find<R: Rules>(
+        board: &[u64; 2],
+        rules: &R,
+        turn: i32,
+        max_plies: u32,
+    ) -> Option<Puzzle> {
+        match shortest_win(board, rules, max_plies) {
+            Some((plies, ref moves)) if plies >= MIN_PUZZLE_PLIES && moves.len() == 1 => Some(Puzzle {
+                board: *board,
+                geo: rules.geometry(),
+                turn,
+                plies,
+                solution: main_line(board, rules, plies),
+            }),
+            _ => None,
+        }
+    }
+
+    // "<position> win in <moves>: <solution>", see `position_name` and
+    // `record_name`; `rules` are the ones the puzzle was found under
+    pub fn to_text<R: Rules>(&self, rules: &R) -> String {
+        format!(
+            "{} win in {}: {}",
+            position_name(&self.board, self.geo, self.turn),
+            (self.plies + 1) / 2,
+            record_name(&self.board, rules, &self.solution)
+        )
+    }
+}
+
+// plays `games` games from `board` (relative to `turn`) with `iterations`
+// per move and collects the puzzles met on the way, each position once
+pub fn generate_puzzles<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    turn: i32,
+    games: u32,
+    iterations: usize,
+    max_plies: u32,
+    seed: u64,
+) -> Vec<Puzzle> {
+    let mut puzzles = vec![];
+    let mut seen = HashSet::new();
+    let mut state = seed;
+
+    for _game in 0..games {
+        let mut board = *board;
+        let mut turn = turn;
+        let mut history = GameHistory::new(DrawRules::default());
+
+        for _ply in 0..MAX_GAME_PLIES {
+            if rules.is_win_state(&board).is_some() || legal_moves_of(&board, rules).is_empty() {
+                break;
+            }
+            if let Some(puzzle) = Puzzle::find(&board, rules, turn, max_plies) {
+                if seen.insert(board.position_hash(turn)) {
+                    puzzles.push(puzzle);
+                }
+            }
+
+            let seed = Some(splitmix64(&mut state));
+            let best = match search(&board, rules, &history, iterations, seed, false) {
+                Some(result) => result.best,
+                None => break,
+            };
+            history.push(&board);
+            let (src, dst) = ((best.x0, best.y0).to_coord(), (best.x1, best.y1).to_coord());
+            board = rules.apply_move(&board, src, dst);
+            turn ^= 1;
+        }
+    }
+    puzzles
+}
+
+// every move of `turn` that wins within `plies` plies
+#[wasm_bindgen]
+pub fn winning_moves(turn: i32, sparse: &[i32], plies: u32) -> JsValue {
+    let rules = Standard::default();
+    let moves: Vec<Move> = match rules.geometry().from_sparse(sparse, turn) {
+        Some(board) => winning_moves_of(&board, &rules, plies)
+            .into_iter()
+            .map(|(src, dst)| Move::from_coords(src, dst))
+            .collect(),
+        None => vec![],
+    };
+    JsValue::from_serde(&moves).unwrap()
+}
+
+// `generate_puzzles` from `sparse`, one puzzle per line in `Puzzle::to_text`
+#[wasm_bindgen]
+pub fn generate_puzzle_text(
+    turn: i32,
+    sparse: &[i32],
+    games: u32,
+    iterations: usize,
+    max_plies: u32,
+    seed: u32,
+) -> String {
+    let rules = Standard::default();
+    let board = match rules.geometry().from_sparse(sparse, turn) {
+        Some(board) => board,
+        None => return String::new(),
+    };
+    generate_puzzles(&board, &rules, turn, games, iterations, max_plies, seed as u64)
+        .iter()
+        .map(|puzzle| puzzle.to_text(&rules))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(squares: &[(i32, i32)]) -> u64 {
+        squares.iter().fold(0, |mask, &sq| mask | sq.to_coord().to_piece())
+    }
+
+    #[test]
+    fn mate_in_two() {
+        let rules = Standard::default();
+        let board = [
+            pieces(&[(3, 1), (0, 2), (0, 3)]),
+            pieces(&[(1, 2), (1, 5), (6, 7), (7, 7)]),
+        ];
+        let (src, dst) = ((3, 1).to_coord(), (2, 1).to_coord());
+        assert!(winning_moves_of(&board, &rules, 1).is_empty());
+        assert_eq!(winning_moves_of(&board, &rules, 3), vec![(src, dst)]);
+        assert_eq!(shortest_win(&board, &rules, 5), Some((3, vec![(src, dst)])));
+    }
+}
@@ -0,0 +1,188 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub(crate) struct Square {
+    x: i32,
+    y: i32,
+}
+
+pub fn legal_destinations_of<R: Rules>(board: &[u64; 2], rules: &R, pos: i32) -> Vec<i32> {
+    rules.destinations(board, pos)
+}
+
+pub fn legal_moves_of<R: Rules>(board: &[u64; 2], rules: &R) -> Vec<(i32, i32)> {
+    rules.moves(board)
+}
+
+pub fn is_legal_move_of<R: Rules>(board: &[u64; 2], rules: &R, src: i32, dst: i32) -> bool {
+    legal_destinations_of(board, rules, src).contains(&dst)
+}
+
+// all squares the piece on (x, y) can move to, as `{ x, y }` objects
+#[wasm_bindgen]
+pub fn legal_destinations(turn: i32, sparse: &[i32], x: i32, y: i32) -> JsValue {
+    let rules = Standard::default();
+    let (geo, pos) = (rules.geometry(), (x, y).to_coord());
+    let squares: Vec<Square> = match geo.from_sparse(sparse, turn) {
+        Some(board) if geo.contains(x, y) => legal_destinations_of(&board, &rules, pos)
+            .into_iter()
+            .map(|dst| {
+                let (x, y) = dst.to_coord_2d();
+                Square { x, y }
+            })
+            .collect(),
+        _ => vec![],
+    };
+    JsValue::from_serde(&squares).unwrap()
+}
+
+// all moves of `turn`, as `{ x0, y0, x1, y1 }` objects
+#[wasm_bindgen]
+pub fn legal_moves(turn: i32, sparse: &[i32]) -> JsValue {
+    let rules = Standard::default();
+    let moves: Vec<Move> = match rules.geometry().from_sparse(sparse, turn) {
+        Some(board) => legal_moves_of(&board, &rules)
+            .into_iter()
+            .map(|(src, dst)| Move::from_coords(src, dst))
+            .collect(),
+        None => vec![],
+    };
+    JsValue::from_serde(&moves).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn is_legal_move(turn: i32, sparse: &[i32], m: &Move) -> bool {
+    let rules = Standard::default();
+    let geo = rules.geometry();
+    match geo.from_sparse(sparse, turn) {
+        Some(board) if geo.contains(m.x0, m.y0) && geo.contains(m.x1, m.y1) => {
+            is_legal_move_of(&board, &rules, (m.x0, m.y0).to_coord(), (m.x1, m.y1).to_coord())
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ongoing,
+    Black,
+    White,
+    Draw,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct GameStatus {
+    status: Status,
+    // connected groups of black and white pieces, each a list of squares
+    groups: [Vec<Vec<Square>>; 2],
+}
+
+pub(crate) fn squares_of(mask: u64) -> Vec<Square> {
+    (0..64)
+        .filter(|pos: &i32| (mask & pos.to_piece()) != 0)
+        .map(|pos| {
+            let (x, y) = pos.to_coord_2d();
+            Square { x, y }
+        })
+        .collect()
+}
+
+// `board` is relative to the side that just moved. A move that connects
+// both sides at once is a win for the side to move after it, not for
+// `mover`: the rule `is_win_state` applies in the search.
+pub fn status_of<R: Rules>(board: &[u64; 2], rules: &R, mover: i32) -> Status {
+    let side = |id: usize| {
+        if (id as i32 ^ mover) == 0 {
+            Status::Black
+        } else {
+            Status::White
+        }
+    };
+    // from the side to move, as the search sees it
+    match rules.is_win_state(&[board[1], board[0]]) {
+        Some(true) => side(1),
+        Some(false) => side(0),
+        None => {
+            let stuck = |id: usize| {
+                let board = if id == 0 { *board } else { [board[1], board[0]] };
+                legal_moves_of(&board, rules).is_empty()
+            };
+            if stuck(0) && stuck(1) {
+                Status::Draw
+            } else {
+                Status::Ongoing
+            }
+        }
+    }
+}
+
+// status of the game after `mover` (0 for black, 1 for white) has moved,
+// as `{ status, groups: [black, white] }`
+#[wasm_bindgen]
+pub fn game_status(sparse: &[i32], mover: i32) -> JsValue {
+    let rules = Standard::default();
+    let board = match rules.geometry().from_sparse(sparse, mover) {
+        Some(board) => board,
+        None => return JsValue::NULL,
+    };
+    let groups = |id: usize| {
+        board[id]
+            .groups()
+            .into_iter()
+            .map(squares_of)
+            .collect::<Vec<_>>()
+    };
+    let (own, other) = (groups(0), groups(1));
+    let groups = if mover == 0 {
+        [own, other]
+    } else {
+        [other, own]
+    };
+    JsValue::from_serde(&GameStatus {
+        status: status_of(&board, &rules, mover),
+        groups,
+    })
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(squares: &[(i32, i32)]) -> u64 {
+        squares.iter().fold(0, |mask, &sq| mask | sq.to_coord().to_piece())
+    }
+
+    #[test]
+    fn mover_connects() {
+        let board = [pieces(&[(0, 0), (1, 1)]), pieces(&[(5, 5), (7, 7)])];
+        assert_eq!(status_of(&board, &Standard::default(), 0), Status::Black);
+        assert_eq!(status_of(&board, &Standard::default(), 1), Status::White);
+    }
+
+    #[test]
+    fn neither_connects() {
+        let board = [pieces(&[(0, 0), (3, 3)]), pieces(&[(5, 5), (7, 7)])];
+        assert_eq!(status_of(&board, &Standard::default(), 0), Status::Ongoing);
+    }
+
+    #[test]
+    fn one_piece_left_is_connected() {
+        // the mover captured all but one piece, which is a group by itself
+        let board = [pieces(&[(0, 0), (7, 7)]), pieces(&[(3, 3)])];
+        let rules = Standard::default();
+        assert_eq!(status_of(&board, &rules, 0), Status::White);
+        assert_eq!(rules.is_win_state(&[board[1], board[0]]), Some(true));
+    }
+
+    #[test]
+    fn simultaneous_connection_follows_the_search() {
+        // a capture that connects both sides at once
+        let board = [pieces(&[(0, 0), (1, 0)]), pieces(&[(5, 5), (6, 6)])];
+        let rules = Standard::default();
+        // the search scores it for the side to move
+        assert_eq!(rules.is_win_state(&[board[1], board[0]]), Some(true));
+        assert_eq!(status_of(&board, &rules, 0), Status::White);
+    }
+}
@@ -0,0 +1,250 @@
+use super::*;
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchStats {
+    pub iterations: u32,
+    pub hits: u32,
+    pub term_expansions: u32,
+    pub undecided: u32,
+    pub expand_depths: Vec<u32>,
+    pub simulate_depths: Vec<u32>,
+}
+
+impl SearchStats {
+    pub(crate) fn add(&mut self, pass: &MCTSSearchPass) {
+        self.iterations += 1;
+        if pass.hit {
+            self.hits += 1;
+        }
+        if pass.expand_term {
+            self.term_expansions += 1;
+        }
+        self.undecided += pass.undecided;
+
+        let depth = pass.expand_depth as usize;
+        if self.expand_depths.len() <= depth {
+            self.expand_depths.resize(depth + 1, 0);
+        }
+        self.expand_depths[depth] += 1;
+
+        if self.simulate_depths.is_empty() {
+            self.simulate_depths.resize(MAX_STEP + 1, 0);
+        }
+        for (total, cnt) in self.simulate_depths.iter_mut().zip(pass.simulate_depths.iter()) {
+            *total += cnt;
+        }
+    }
+
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.iterations += other.iterations;
+        self.hits += other.hits;
+        self.term_expansions += other.term_expansions;
+        self.undecided += other.undecided;
+        for (hist, other) in [
+            (&mut self.expand_depths, &other.expand_depths),
+            (&mut self.simulate_depths, &other.simulate_depths),
+        ]
+        .iter_mut()
+        {
+            if hist.len() < other.len() {
+                hist.resize(other.len(), 0);
+            }
+            for (total, cnt) in hist.iter_mut().zip(other.iter()) {
+                *total += cnt;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChildStat {
+    #[serde(rename = "move")]
+    pub mv: Move,
+    pub visits: f32,
+    // mean value for the side to move at the root
+    pub value: f32,
+    pub proven: Proven,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    pub best: Move,
+    pub visits: f32,
+    pub win_rate: f32,
+    pub children: Vec<ChildStat>,
+    pub pv: Vec<Move>,
+    pub iterations: u32,
+    pub hits: u32,
+    pub term_expansions: u32,
+    // histograms indexed by depth
+    pub expand_depths: Vec<u32>,
+    pub simulate_depths: Vec<u32>,
+    pub undecided: u32,
+    pub elapsed_ms: f64,
+}
+
+impl SearchNode {
+    pub fn most_visited(&self) -> Option<&SearchNode> {
+        match self.data {
+            Some((SearchNodeData::Mid { ref childs, .. }, ..)) => childs
+                .iter()
+                .filter(|child| child.data.is_some())
+                .fold(None, |best: Option<&SearchNode>, child| match best {
+                    Some(best) if best.visits() >= child.visits() => Some(best),
+                    _ => Some(child),
+                }),
+            _ => None,
+        }
+    }
+
+    // follows the most visited child down the tree
+    pub fn principal_variation(&self) -> Vec<(i32, i32)> {
+        let mut pv = vec![];
+        let mut node = self;
+        while let Some(child) = node.most_visited() {
+            pv.push(child.curr_move);
+            node = child;
+        }
+        pv
+    }
+
+    pub fn visits(&self) -> f32 {
+        self.data.as_ref().map_or(0f32, |(.., b)| *b)
+    }
+
+    // for the side to move at this node, even before it is visited
+    pub fn win_rate(&self) -> f32 {
+        match self.data {
+            Some((.., a, b)) if b > 0f32 => a / b,
+            _ => 0.5f32,
+        }
+    }
+}
+
+impl SearchResult {
+    // None if no root move has been visited: the budget was 0, or the game
+    // is already over at the root
+    pub fn new(root: &mut SearchNode, stats: SearchStats, elapsed_ms: f64) -> Option<Self> {
+        let (src, dst) = root.find_max(true).map(|ptr| unsafe { (*ptr).curr_move })?;
+        let best = Move::from_coords(src, dst);
+        let (visits, win_rate) = (root.visits(), root.win_rate());
+
+        let children = match root.data {
+            Some((SearchNodeData::Mid { ref childs, .. }, ..)) => childs
+                .iter()
+                .filter_map(|child| {
+                    child.data.as_ref().map(|(.., a, b)| {
+                        let (src, dst) = child.curr_move;
+                        ChildStat {
+                            mv: Move::from_coords(src, dst),
+                            visits: *b,
+                            value: 1f32 - a / b,
+                            proven: child.proven(),
+                        }
+                    })
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        let pv = root
+            .principal_variation()
+            .into_iter()
+            .map(|(src, dst)| Move::from_coords(src, dst))
+            .collect();
+
+        Some(SearchResult {
+            best,
+            visits,
+            win_rate,
+            children,
+            pv,
+            iterations: stats.iterations,
+            hits: stats.hits,
+            term_expansions: stats.term_expansions,
+            expand_depths: stats.expand_depths,
+            simulate_depths: stats.simulate_depths,
+            undecided: stats.undecided,
+            elapsed_ms,
+        })
+    }
+
+    pub fn log(&self) {
+        let all = self.iterations;
+
+        log(&format!("{} estimated win rate", self.win_rate));
+
+        log(&format!("{} hits of {}", self.hits, all));
+        log(&format!("{} term expansion of {}", self.term_expansions, all));
+
+        let summary = |hist: &[u32]| {
+            let total: u32 = hist.iter().sum();
+            let sum: u32 = hist.iter().enumerate().map(|(d, n)| d as u32 * n).sum();
+            let max = hist.iter().rposition(|&n| n != 0).unwrap_or(0);
+            let min = hist.iter().position(|&n| n != 0).unwrap_or(0);
+            (sum as f32 / total.max(1) as f32, max, min)
+        };
+
+        let (avg, max, min) = summary(&self.expand_depths);
+        log(&format!("{} average expand depth", avg));
+        log(&format!("{} max expand depth", max));
+        log(&format!("{} min expand depth", min));
+
+        let (avg, max, min) = summary(&self.simulate_depths);
+        log(&format!("{} average simulate depth", avg));
+        log(&format!("{} max simulate depth", max));
+        log(&format!("{} min simulate depth", min));
+
+        log(&format!("{} undecided playouts", self.undecided));
+        log(&format!("{} ms elapsed", self.elapsed_ms));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_result_without_visits() {
+        let rules = Standard::default();
+        let history = GameHistory::new(DrawRules::default());
+        let board = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        assert!(search(&board, &rules, &history, 0, Some(1), false).is_none());
+        assert!(search(&board, &rules, &history, 8, Some(1), false).is_some());
+    }
+
+    #[test]
+    fn same_seed_same_result() {
+        let rules = Standard::default();
+        let history = GameHistory::new(DrawRules::default());
+        let board = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        let summary = |seed| {
+            let result = search(&board, &rules, &history, 500, Some(seed), false).unwrap();
+            let children: Vec<(Move, f32, f32)> = result
+                .children
+                .iter()
+                .map(|child| (child.mv.clone(), child.visits, child.value))
+                .collect();
+            (result.best, result.visits, result.win_rate, children, result.pv, result.hits)
+        };
+        assert_eq!(summary(3), summary(3));
+        assert_ne!(summary(3), summary(4));
+    }
+
+    #[test]
+    fn no_move_for_a_board_of_another_size() {
+        let sparse = [-1; 36];
+        assert!(my_plain_solution(0, &sparse, Some(1), None).is_none());
+        let rules = DrawRules::default();
+        assert!(my_plain_solution_with_history(0, &sparse, &[], &rules, Some(1), None).is_none());
+    }
+
+    #[test]
+    fn no_result_once_the_game_is_over() {
+        let rules = Standard::default();
+        let history = GameHistory::new(DrawRules::default());
+        // both sides down to a single group
+        let board = [0b11u64, 0b11u64 << 62];
+        assert!(search(&board, &rules, &history, 64, Some(1), false).is_none());
+    }
+}
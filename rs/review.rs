@@ -0,0 +1,232 @@
+use super::*;
+
+use policy::FinalPolicy;
+
+// keeps every position of a game to a few seconds
+const GAME_ITERATIONS: usize = 4096;
+// drops in the mover's win rate that make a move a mistake or a blunder
+const MISTAKE_DROP: f32 = 0.1f32;
+const BLUNDER_DROP: f32 = 0.25f32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameAnalysisConfig {
+    // search budget for every position
+    pub iterations: usize,
+    pub mistake: f32,
+    pub blunder: f32,
+    pub seed: Option<u64>,
+}
+
+impl Default for GameAnalysisConfig {
+    fn default() -> Self {
+        GameAnalysisConfig {
+            iterations: GAME_ITERATIONS,
+            mistake: MISTAKE_DROP,
+            blunder: BLUNDER_DROP,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MoveQuality {
+    Good,
+    Mistake,
+    Blunder,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoveAnalysis {
+    #[serde(rename = "move")]
+    pub mv: Move,
+    // in notation
+    pub name: String,
+    // win rate of the side that played the move, before and after it
+    pub before: f32,
+    pub after: f32,
+    pub quality: MoveQuality,
+    // the move the engine would have played, None if it is the one played
+    pub best: Option<Move>,
+    pub best_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameAnalysis {
+    // win rate of the side that moved first, at the start and after every
+    // move, for drawing a graph
+    pub win_rates: Vec<f32>,
+    pub moves: Vec<MoveAnalysis>,
+}
+
+// the win rate of the side to move on `board` and its best move; no move
+// once the game is over
+fn evaluate<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    iterations: usize,
+    seed: Option<u64>,
+) -> (f32, Option<(i32, i32)>) {
+    if let Some(win) = rules.is_win_state(board) {
+        return (if win { 1f32 } else { 0f32 }, None);
+    }
+    // passing is not modelled, a stuck side draws
+    if rules.moves(board).is_empty() {
+        return (0.5f32, None);
+    }
+    let (root, _stats) = search_tree(board, rules, history, iterations, seed);
+    let value = match root.data {
+        Some((.., a, b)) if b > 0f32 => a / b,
+        _ => 0.5f32,
+    };
+    let mut rng = RngProvider::with_seed(seed);
+    (value, root.final_move(FinalPolicy::default(), &mut rng))
+}
+
+// searches every position of the game `moves` from `board` (relative to the
+// first mover) with the same budget and judges each move by how much it
+// lowered its player's win rate; Err names the first illegal move, or says
+// the budget is empty
+pub fn analyse_game<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    moves: &[(i32, i32)],
+    config: &GameAnalysisConfig,
+) -> Result<GameAnalysis, String> {
+    // without a search every position would look finished
+    if config.iterations == 0 {
+        return Err("the analysis needs at least one iteration per position".to_string());
+    }
+    let geo = rules.geometry();
+    let seed_of = |ply: usize| config.seed.map(|seed| seed.wrapping_add(ply as u64));
+    let mut board = *board;
+    let mut history = GameHistory::new(DrawRules::default());
+    let mut analysis = GameAnalysis {
+        win_rates: vec![],
+        moves: vec![],
+    };
+
+    let (mut value, mut best) = evaluate(&board, rules, &history, config.iterations, seed_of(0));
+    analysis.win_rates.push(value);
+    for (ply, &(src, dst)) in moves.iter().enumerate() {
+        let name = move_name(&board, geo, src, dst);
+        if best.is_none() {
+            return Err(format!("move {} at ply {} after the game is over", name, ply + 1));
+        }
+        if !rules.moves(&board).contains(&(src, dst)) {
+            return Err(format!("illegal move {} at ply {}", name, ply + 1));
+        }
+
+        let next = rules.apply_move(&board, src, dst);
+        history.push(&board);
+        let (next_value, next_best) =
+            evaluate(&next, rules, &history, config.iterations, seed_of(ply + 1));
+
+        let after = 1f32 - next_value;
+        let drop = value - after;
+        let quality = if drop > config.blunder {
+            MoveQuality::Blunder
+        } else if drop > config.mistake {
+            MoveQuality::Mistake
+        } else {
+            MoveQuality::Good
+        };
+        let other = best.filter(|&mv| mv != (src, dst));
+        analysis.moves.push(MoveAnalysis {
+            mv: Move::from_coords(src, dst),
+            name,
+            before: value,
+            after,
+            quality,
+            best: other.map(|(src, dst)| Move::from_coords(src, dst)),
+            best_name: other.map(|(src, dst)| move_name(&board, geo, src, dst)),
+        });
+        // the first mover plays the even plies
+        analysis.win_rates.push(if ply % 2 == 0 { after } else { next_value });
+
+        board = next;
+        value = next_value;
+        best = next_best;
+    }
+    Ok(analysis)
+}
+
+impl GameAnalysis {
+    // the record with "?" after mistakes and "??" after blunders, each
+    // followed by the change in win rate and the engine's move, like
+    // "1. b1-b3 g1-e3? {0.52 -> 0.38, better h2-f2}"
+    pub fn annotated_record(&self) -> String {
+        let mut tokens = vec![];
+        for (ply, mv) in self.moves.iter().enumerate() {
+            if ply % 2 == 0 {
+                tokens.push(format!("{}.", ply / 2 + 1));
+            }
+            let mark = match mv.quality {
+                MoveQuality::Good => {
+                    tokens.push(mv.name.clone());
+                    continue;
+                }
+                MoveQuality::Mistake => "?",
+                MoveQuality::Blunder => "??",
+            };
+            tokens.push(format!("{}{}", mv.name, mark));
+            let better = mv.best_name.as_ref().map_or_else(String::new, |best| {
+                format!(", better {}", best)
+            });
+            tokens.push(format!("{{{:.2} -> {:.2}{}}}", mv.before, mv.after, better));
+        }
+        tokens.join(" ")
+    }
+}
+
+// `analyse_game` of a record in notation from a position string, see
+// `parse_position`, under a named variant; a `GameAnalysis` object
+#[wasm_bindgen]
+pub fn analyse_game_record(
+    position: &str,
+    variant: &str,
+    record: &str,
+    iterations: u32,
+    seed: Option<u32>,
+) -> Result<JsValue, JsValue> {
+    let (geo, board, _turn) = parse_position(position).map_err(|err| JsValue::from_str(&err))?;
+    let rules = Variant::from_name(variant, geo)
+        .ok_or_else(|| JsValue::from_str(&format!("unknown variant `{}`", variant)))?;
+    let moves = parse_record(&rules, record).map_err(|err| JsValue::from_str(&err))?;
+    let config = GameAnalysisConfig {
+        iterations: iterations as usize,
+        seed: seed.map(u64::from),
+        ..GameAnalysisConfig::default()
+    };
+    let analysis =
+        analyse_game(&board, &rules, &moves, &config).map_err(|err| JsValue::from_str(&err))?;
+    Ok(JsValue::from_serde(&analysis).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_a_search_budget() {
+        let rules = Standard::default();
+        let board = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        let moves = parse_record(&rules, "1. b1-b3").unwrap();
+        let empty = GameAnalysisConfig {
+            iterations: 0,
+            ..GameAnalysisConfig::default()
+        };
+        let err = analyse_game(&board, &rules, &moves, &empty).unwrap_err();
+        assert!(err.contains("iteration"), "{}", err);
+
+        let small = GameAnalysisConfig {
+            iterations: 50,
+            seed: Some(1),
+            ..GameAnalysisConfig::default()
+        };
+        let analysis = analyse_game(&board, &rules, &moves, &small).unwrap();
+        assert_eq!(analysis.win_rates.len(), 2);
+        assert_eq!(analysis.moves.len(), 1);
+    }
+}
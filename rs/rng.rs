@@ -0,0 +1,193 @@
+#[cfg(target_arch = "wasm32")]
+use wbg_rand::{wasm_rng, Rng};
+
+const RNG_PHASE: u32 = 8;
+const RNG_SHAMT: u32 = 8;
+const RNG_MASK: u32 = (1u32 << RNG_SHAMT) - 1;
+
+pub trait U64Rng {
+    fn next(&mut self) -> u64;
+    fn from_seed(seed: u64) -> Self;
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_seed(entropy())
+    }
+}
+
+// a fresh seed for unseeded searches
+#[cfg(target_arch = "wasm32")]
+fn entropy() -> u64 {
+    wasm_rng().next_u64()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn entropy() -> u64 {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    // tells apart sessions created within the same clock tick
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed) as u64;
+    splitmix64(&mut (time.as_secs() ^ (time.subsec_nanos() as u64) << 32 ^ count << 48))
+}
+
+pub fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15u64);
+    let mut x = *state;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9u64);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111ebu64);
+    x ^ (x >> 31)
+}
+
+// xoshiro256**, see http://prng.di.unimi.it/
+#[derive(Debug, Clone)]
+pub struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl U64Rng for Xoshiro256 {
+    fn next(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        result
+    }
+    fn from_seed(seed: u64) -> Self {
+        let mut state = seed;
+        Xoshiro256 {
+            s: [
+                splitmix64(&mut state),
+                splitmix64(&mut state),
+                splitmix64(&mut state),
+                splitmix64(&mut state),
+            ],
+        }
+    }
+}
+
+// hands out 8 random bits per call, one u64 lasts 8 calls
+#[derive(Debug, Clone)]
+pub struct MyRng<T: U64Rng> {
+    curr: u64,
+    phase: u32,
+    rng: T,
+}
+
+impl<T: U64Rng> MyRng<T> {
+    pub fn next_u32(&mut self) -> u32 {
+        if self.phase == 0 {
+            self.curr = self.rng.next();
+        }
+        self.phase = (self.phase + 1) & (RNG_PHASE - 1);
+        let val = self.curr as u32 & RNG_MASK;
+        self.curr >>= RNG_SHAMT;
+        val
+    }
+    // roughly uniform in 0..n, from four calls of `next_u32`
+    pub fn below(&mut self, n: u32) -> u32 {
+        let mut val = 0u32;
+        for _i in 0..4 {
+            val = val << RNG_SHAMT | self.next_u32();
+        }
+        val % n.max(1)
+    }
+    pub fn new() -> Self {
+        MyRng::with_rng(T::new())
+    }
+    pub fn from_seed(seed: u64) -> Self {
+        MyRng::with_rng(T::from_seed(seed))
+    }
+    // seeded if `seed` is given, otherwise from entropy
+    pub fn with_seed(seed: Option<u64>) -> Self {
+        seed.map_or_else(MyRng::new, MyRng::from_seed)
+    }
+    fn with_rng(rng: T) -> Self {
+        MyRng {
+            curr: 0,
+            phase: 0,
+            rng,
+        }
+    }
+}
+
+pub type RngProvider = MyRng<Xoshiro256>;
+
+impl RngProvider {
+    // the whole state, for saving a search; see `from_words`
+    pub fn to_words(&self) -> [u64; 6] {
+        let s = &self.rng.s;
+        [s[0], s[1], s[2], s[3], self.curr, self.phase as u64]
+    }
+
+    // None if `words` could not have come from `to_words`
+    pub fn from_words(words: &[u64; 6]) -> Option<Self> {
+        if words[..4].iter().all(|&word| word == 0) || words[5] >= RNG_PHASE as u64 {
+            return None;
+        }
+        Some(MyRng {
+            curr: words[4],
+            phase: words[5] as u32,
+            rng: Xoshiro256 {
+                s: [words[0], words[1], words[2], words[3]],
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splitmix64_reference() {
+        let mut state = 0u64;
+        let outputs: Vec<u64> = (0..3).map(|_i| splitmix64(&mut state)).collect();
+        assert_eq!(outputs, [0xe220a8397b1dcdaf, 0x6e789e6aa1b965f4, 0x06c45d188009454f]);
+    }
+
+    #[test]
+    fn xoshiro256_reference() {
+        // the first outputs from the state [1, 2, 3, 4]
+        let mut rng = Xoshiro256 { s: [1, 2, 3, 4] };
+        let outputs: Vec<u64> = (0..10).map(|_i| rng.next()).collect();
+        assert_eq!(
+            outputs,
+            [
+                11520,
+                0,
+                1509978240,
+                1215971899390074240,
+                1216172134540287360,
+                607988272756665600,
+                16172922978634559625,
+                8476171486693032832,
+                10595114339597558777,
+                2904607092377533576,
+            ]
+        );
+    }
+
+    #[test]
+    fn words_round_trip() {
+        let mut rng = RngProvider::from_seed(9);
+        rng.below(100);
+        let mut copy = RngProvider::from_words(&rng.to_words()).unwrap();
+        for _i in 0..32 {
+            assert_eq!(rng.next_u32(), copy.next_u32());
+        }
+        assert!(RngProvider::from_words(&[0; 6]).is_none());
+    }
+}
@@ -0,0 +1,289 @@
+use super::*;
+
+// the rules of a variant; boards are relative to the side to move as
+// everywhere else, and every method but `geometry` defaults to standard LOA
+pub trait Rules {
+    fn geometry(&self) -> Geometry;
+
+    // the squares the piece on `pos` can move to
+    fn destinations(&self, board: &[u64; 2], pos: i32) -> Vec<i32> {
+        line_destinations(board, self.geometry(), pos)
+    }
+
+    // the first of `destinations`; the rules below find it without
+    // collecting the others, as playouts only ever need one
+    fn first_destination(&self, board: &[u64; 2], pos: i32) -> Option<i32> {
+        self.destinations(board, pos).first().cloned()
+    }
+
+    // appends the moves of the piece on `pos` to `res`
+    fn push_moves(&self, board: &[u64; 2], pos: i32, res: &mut Vec<(i32, i32)>) {
+        res.extend(self.destinations(board, pos).into_iter().map(|dst| (pos, dst)));
+    }
+
+    // the board after the move, relative to the other side
+    fn apply_move(&self, board: &[u64; 2], src: i32, dst: i32) -> [u64; 2] {
+        board.apply_move(src, dst)
+    }
+
+    // Some(true) if the side to move has won, Some(false) if it has lost;
+    // with both sides connected the side to move has won
+    fn is_win_state(&self, board: &[u64; 2]) -> Option<bool> {
+        board.is_win_state()
+    }
+
+    fn moves(&self, board: &[u64; 2]) -> Vec<(i32, i32)> {
+        let mut res = vec![];
+        let mut pieces = board[0];
+        while pieces != 0 {
+            let pos = pieces.trailing_zeros() as i32;
+            self.push_moves(board, pos, &mut res);
+            pieces &= pieces - 1;
+        }
+        res
+    }
+
+    // the first move of the first piece that has one, from a random square
+    // on; `EMPTY_MOVE` if there is none
+    fn rand_move(&self, board: &[u64; 2], rng: &mut RngProvider) -> (i32, i32) {
+        let rn: i32 = rng.next_u32() as i32 & 0x3fi32;
+        let b = board[0];
+
+        for i in (rn..64).chain(0..rn).filter(|i| (i.to_piece() & b) != 0) {
+            if let Some(dst) = self.first_destination(board, i) {
+                return (i, dst);
+            }
+        }
+
+        EMPTY_MOVE
+    }
+}
+
+// moves along the lines of action, see `gen_moves`
+pub fn line_destinations(board: &[u64; 2], geo: Geometry, pos: i32) -> Vec<i32> {
+    let mut res = vec![];
+    let mut moves = gen_moves(board, geo, 0, pos);
+    while let GeneratorState::Yielded(dst) = Pin::new(&mut moves).resume() {
+        res.push(dst);
+    }
+    res
+}
+
+// the first of `line_destinations` that `keep` lets through
+fn first_line_destination<F: Fn(i32) -> bool>(
+    board: &[u64; 2],
+    geo: Geometry,
+    pos: i32,
+    keep: F,
+) -> Option<i32> {
+    let mut moves = gen_moves(board, geo, 0, pos);
+    while let GeneratorState::Yielded(dst) = Pin::new(&mut moves).resume() {
+        if keep(dst) {
+            return Some(dst);
+        }
+    }
+    None
+}
+
+// appends the `line_destinations` that `keep` lets through to `res`
+fn push_line_moves<F: Fn(i32) -> bool>(
+    board: &[u64; 2],
+    geo: Geometry,
+    pos: i32,
+    keep: F,
+    res: &mut Vec<(i32, i32)>,
+) {
+    let mut moves = gen_moves(board, geo, 0, pos);
+    while let GeneratorState::Yielded(dst) = Pin::new(&mut moves).resume() {
+        if keep(dst) {
+            res.push((pos, dst));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Standard {
+    pub geo: Geometry,
+}
+
+impl Rules for Standard {
+    fn geometry(&self) -> Geometry {
+        self.geo
+    }
+
+    fn first_destination(&self, board: &[u64; 2], pos: i32) -> Option<i32> {
+        first_line_destination(board, self.geo, pos, |_dst| true)
+    }
+
+    fn push_moves(&self, board: &[u64; 2], pos: i32, res: &mut Vec<(i32, i32)>) {
+        push_line_moves(board, self.geo, pos, |_dst| true, res);
+    }
+}
+
+// Black Hole LOA: a piece moving onto the centre square is taken off the
+// board, see `hole` for even sizes
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BlackHole {
+    pub geo: Geometry,
+}
+
+impl BlackHole {
+    // ((size - 1) / 2, (size - 1) / 2): the centre on odd sizes; even sizes
+    // have four centre squares and the hole is the one up and left, d5 on
+    // the 8x8 board
+    pub fn hole(&self) -> i32 {
+        let centre = (self.geo.size() - 1) / 2;
+        (centre, centre).to_coord()
+    }
+}
+
+impl Rules for BlackHole {
+    fn geometry(&self) -> Geometry {
+        self.geo
+    }
+
+    fn first_destination(&self, board: &[u64; 2], pos: i32) -> Option<i32> {
+        first_line_destination(board, self.geo, pos, |_dst| true)
+    }
+
+    fn push_moves(&self, board: &[u64; 2], pos: i32, res: &mut Vec<(i32, i32)>) {
+        push_line_moves(board, self.geo, pos, |_dst| true, res);
+    }
+
+    fn apply_move(&self, board: &[u64; 2], src: i32, dst: i32) -> [u64; 2] {
+        let mut next = board.apply_move(src, dst);
+        next[1] &= !self.hole().to_piece();
+        next
+    }
+}
+
+// pieces may not land on enemy pieces, so nothing is ever captured
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NoCapture {
+    pub geo: Geometry,
+}
+
+impl Rules for NoCapture {
+    fn geometry(&self) -> Geometry {
+        self.geo
+    }
+
+    fn destinations(&self, board: &[u64; 2], pos: i32) -> Vec<i32> {
+        line_destinations(board, self.geo, pos)
+            .into_iter()
+            .filter(|dst| (board[1] & dst.to_piece()) == 0)
+            .collect()
+    }
+
+    fn first_destination(&self, board: &[u64; 2], pos: i32) -> Option<i32> {
+        first_line_destination(board, self.geo, pos, |dst| (board[1] & dst.to_piece()) == 0)
+    }
+
+    fn push_moves(&self, board: &[u64; 2], pos: i32, res: &mut Vec<(i32, i32)>) {
+        push_line_moves(board, self.geo, pos, |dst| (board[1] & dst.to_piece()) == 0, res);
+    }
+}
+
+// one of the built-in variants, for where the rules can only be picked at
+// run time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    Standard(Standard),
+    BlackHole(BlackHole),
+    NoCapture(NoCapture),
+}
+
+impl Variant {
+    pub fn from_name(name: &str, geo: Geometry) -> Option<Variant> {
+        match name.to_ascii_lowercase().as_str() {
+            "standard" => Some(Variant::Standard(Standard { geo })),
+            "black-hole" => Some(Variant::BlackHole(BlackHole { geo })),
+            "no-capture" => Some(Variant::NoCapture(NoCapture { geo })),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Standard(..) => "standard",
+            Variant::BlackHole(..) => "black-hole",
+            Variant::NoCapture(..) => "no-capture",
+        }
+    }
+
+    fn rules(&self) -> &dyn Rules {
+        match self {
+            Variant::Standard(rules) => rules,
+            Variant::BlackHole(rules) => rules,
+            Variant::NoCapture(rules) => rules,
+        }
+    }
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Variant::Standard(Standard::default())
+    }
+}
+
+impl Rules for Variant {
+    fn geometry(&self) -> Geometry {
+        self.rules().geometry()
+    }
+
+    fn destinations(&self, board: &[u64; 2], pos: i32) -> Vec<i32> {
+        self.rules().destinations(board, pos)
+    }
+
+    fn first_destination(&self, board: &[u64; 2], pos: i32) -> Option<i32> {
+        self.rules().first_destination(board, pos)
+    }
+
+    fn push_moves(&self, board: &[u64; 2], pos: i32, res: &mut Vec<(i32, i32)>) {
+        self.rules().push_moves(board, pos, res)
+    }
+
+    fn apply_move(&self, board: &[u64; 2], src: i32, dst: i32) -> [u64; 2] {
+        self.rules().apply_move(board, src, dst)
+    }
+
+    fn is_win_state(&self, board: &[u64; 2]) -> Option<bool> {
+        self.rules().is_win_state(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playout_moves_match_destinations() {
+        let geo = Geometry::default();
+        let board = setup::Setup::Standard.board(geo, &mut RngProvider::from_seed(0));
+        // an enemy piece in the middle so that captures are on offer
+        let board = [board[0], board[1] | (1 << 27)];
+        for name in ["standard", "black-hole", "no-capture"].iter() {
+            let rules = Variant::from_name(name, geo).unwrap();
+            let mut expected = vec![];
+            for pos in 0..64 {
+                if board[0] & pos.to_piece() == 0 {
+                    continue;
+                }
+                let dsts = rules.destinations(&board, pos);
+                assert_eq!(rules.first_destination(&board, pos), dsts.first().cloned());
+                expected.extend(dsts.into_iter().map(|dst| (pos, dst)));
+            }
+            assert_eq!(rules.moves(&board), expected);
+        }
+    }
+
+    #[test]
+    fn black_hole_square() {
+        let hole = |size| {
+            let geo = Geometry::new(size).unwrap();
+            notation::square_name(geo, BlackHole { geo }.hole())
+        };
+        assert_eq!(hole(8), "d5");
+        assert_eq!(hole(7), "d4");
+    }
+}
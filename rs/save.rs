@@ -0,0 +1,286 @@
+use super::*;
+
+use book::{read_u16, read_u32, read_u64, write_le};
+
+// file layout, all little endian:
+//   "LOAT", version: u16,
+//   variant: str, size: u8, board: 2 * u64,
+//   history: { repetition: u32, move_limit: u32, contempt: f32,
+//              moves_played: u32, count: u32, count * hash: u64 },
+//   rng: 6 * u64,
+//   stats: { iterations, hits, term_expansions, undecided: u32,
+//            expand_depths: list, simulate_depths: list },
+//   elapsed_ms: f64, policy: str, param: f32,
+//   root: node
+// where a str is { len: u8, len * u8 }, a list { len: u32, len * u32 }
+// and a node, children in order after their parent,
+//   { src: u8, dst: u8, kind: u8 (none, mid, lost, won, draw),
+//     if not none: a: f32, b: f32,
+//     if mid: curr: u32, full: u8, board: 2 * u64, count: u32 }
+const TREE_MAGIC: &[u8; 4] = b"LOAT";
+const TREE_VERSION: u16 = 2;
+
+const KIND_NONE: u8 = 0;
+const KIND_MID: u8 = 1;
+const KIND_LOST: u8 = 2;
+const KIND_WON: u8 = 3;
+const KIND_DRAW: u8 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeError {
+    BadMagic,
+    BadVersion(u16),
+    Truncated,
+    // a name, a size or a tag that cannot be read
+    BadField,
+    // a board that does not follow from its parent under the saved rules
+    BadBoard,
+}
+
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], TreeError> {
+        if self.bytes.len() < len {
+            return Err(TreeError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, TreeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, TreeError> {
+        Ok(read_u16(self.take(2)?))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, TreeError> {
+        Ok(read_u32(self.take(4)?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, TreeError> {
+        Ok(read_u64(self.take(8)?))
+    }
+
+    pub(crate) fn f32(&mut self) -> Result<f32, TreeError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    pub(crate) fn f64(&mut self) -> Result<f64, TreeError> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    pub(crate) fn board(&mut self) -> Result<[u64; 2], TreeError> {
+        Ok([self.u64()?, self.u64()?])
+    }
+
+    pub(crate) fn str(&mut self) -> Result<&'a str, TreeError> {
+        let len = self.u8()? as usize;
+        std::str::from_utf8(self.take(len)?).map_err(|_| TreeError::BadField)
+    }
+
+    pub(crate) fn list(&mut self) -> Result<Vec<u32>, TreeError> {
+        let len = self.u32()? as usize;
+        // checked first, so a bad length cannot ask for a huge vector
+        self.take(4 * len)?
+            .chunks(4)
+            .map(|bytes| Ok(read_u32(bytes)))
+            .collect()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+pub(crate) fn write_header(out: &mut Vec<u8>) {
+    out.extend_from_slice(TREE_MAGIC);
+    write_le(out, TREE_VERSION as u64, 2);
+}
+
+pub(crate) fn read_header(reader: &mut Reader) -> Result<(), TreeError> {
+    if reader.take(4)? != TREE_MAGIC {
+        return Err(TreeError::BadMagic);
+    }
+    let version = reader.u16()?;
+    if version != TREE_VERSION {
+        return Err(TreeError::BadVersion(version));
+    }
+    Ok(())
+}
+
+pub(crate) fn write_f32(out: &mut Vec<u8>, val: f32) {
+    write_le(out, val.to_bits() as u64, 4);
+}
+
+pub(crate) fn write_board(out: &mut Vec<u8>, board: &[u64; 2]) {
+    write_le(out, board[0], 8);
+    write_le(out, board[1], 8);
+}
+
+pub(crate) fn write_str(out: &mut Vec<u8>, val: &str) {
+    out.push(val.len() as u8);
+    out.extend_from_slice(val.as_bytes());
+}
+
+pub(crate) fn write_list(out: &mut Vec<u8>, vals: &[u32]) {
+    write_le(out, vals.len() as u64, 4);
+    for &val in vals.iter() {
+        write_le(out, val as u64, 4);
+    }
+}
+
+pub(crate) fn write_history(out: &mut Vec<u8>, history: &GameHistory) {
+    write_le(out, history.rules.repetition as u64, 4);
+    write_le(out, history.rules.move_limit as u64, 4);
+    write_f32(out, history.rules.contempt);
+    write_le(out, history.moves_played as u64, 4);
+    write_le(out, history.hashes.len() as u64, 4);
+    for &hash in history.hashes.iter() {
+        write_le(out, hash, 8);
+    }
+}
+
+pub(crate) fn read_history(reader: &mut Reader) -> Result<GameHistory, TreeError> {
+    let rules = DrawRules::new(reader.u32()?, reader.u32()?, reader.f32()?);
+    let moves_played = reader.u32()?;
+    let len = reader.u32()? as usize;
+    let mut hashes = vec![];
+    for _i in 0..len {
+        hashes.push(reader.u64()?);
+    }
+    Ok(GameHistory {
+        hashes,
+        moves_played,
+        rules,
+    })
+}
+
+pub(crate) fn write_stats(out: &mut Vec<u8>, stats: &SearchStats) {
+    for &val in [stats.iterations, stats.hits, stats.term_expansions, stats.undecided].iter() {
+        write_le(out, val as u64, 4);
+    }
+    write_list(out, &stats.expand_depths);
+    write_list(out, &stats.simulate_depths);
+}
+
+pub(crate) fn read_stats(reader: &mut Reader) -> Result<SearchStats, TreeError> {
+    Ok(SearchStats {
+        iterations: reader.u32()?,
+        hits: reader.u32()?,
+        term_expansions: reader.u32()?,
+        undecided: reader.u32()?,
+        expand_depths: reader.list()?,
+        simulate_depths: reader.list()?,
+    })
+}
+
+pub(crate) fn write_node(out: &mut Vec<u8>, node: &SearchNode) {
+    out.push(node.curr_move.0 as u8);
+    out.push(node.curr_move.1 as u8);
+    let (data, a, b) = match node.data {
+        Some((ref data, a, b)) => (data, a, b),
+        None => {
+            out.push(KIND_NONE);
+            return;
+        }
+    };
+    out.push(match data {
+        SearchNodeData::Mid { .. } => KIND_MID,
+        SearchNodeData::Term(false) => KIND_LOST,
+        SearchNodeData::Term(true) => KIND_WON,
+        SearchNodeData::Draw => KIND_DRAW,
+    });
+    write_f32(out, a);
+    write_f32(out, b);
+    if let SearchNodeData::Mid {
+        curr,
+        full,
+        ref board,
+        ref childs,
+    } = data
+    {
+        write_le(out, *curr as u64, 4);
+        out.push(*full as u8);
+        write_board(out, board);
+        write_le(out, childs.len() as u64, 4);
+        for child in childs.iter() {
+            write_node(out, child);
+        }
+    }
+}
+
+// reads a node whose parent position is `board`; an inner node must hold the
+// position after its move, and its children the moves from there in the
+// order `rules` generates them
+pub(crate) fn read_node<R: Rules>(
+    reader: &mut Reader,
+    board: &[u64; 2],
+    rules: &R,
+    root: bool,
+) -> Result<SearchNode, TreeError> {
+    // checked against the moves of the parent below
+    let curr_move = (reader.u8()? as i32, reader.u8()? as i32);
+    let kind = reader.u8()?;
+    if kind == KIND_NONE {
+        return Ok(SearchNode {
+            curr_move,
+            data: None,
+        });
+    }
+    let (a, b) = (reader.f32()?, reader.f32()?);
+    let data = match kind {
+        KIND_LOST => SearchNodeData::Term(false),
+        KIND_WON => SearchNodeData::Term(true),
+        KIND_DRAW => SearchNodeData::Draw,
+        KIND_MID => {
+            let curr = reader.u32()? as i32;
+            let full = reader.u8()? != 0;
+            let node_board = reader.board()?;
+            let expected = if root {
+                *board
+            } else {
+                rules.apply_move(board, curr_move.0, curr_move.1)
+            };
+            if node_board != expected {
+                return Err(TreeError::BadBoard);
+            }
+
+            let moves = rules.moves(&node_board);
+            if reader.u32()? as usize != moves.len() {
+                return Err(TreeError::BadBoard);
+            }
+            let mut childs = Vec::with_capacity(moves.len());
+            for &mv in moves.iter() {
+                let child = read_node(reader, &node_board, rules, false)?;
+                if child.curr_move != mv {
+                    return Err(TreeError::BadBoard);
+                }
+                childs.push(child);
+            }
+            if curr < 0 || curr as usize >= childs.len().max(1) {
+                return Err(TreeError::BadField);
+            }
+            SearchNodeData::Mid {
+                curr,
+                full,
+                board: node_board,
+                childs,
+            }
+        }
+        _ => return Err(TreeError::BadField),
+    };
+    Ok(SearchNode {
+        curr_move,
+        data: Some((data, a, b)),
+    })
+}
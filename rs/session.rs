@@ -0,0 +1,533 @@
+use super::*;
+
+// passes between two clock reads in `run_for`
+pub(crate) const TIME_CHECK_INTERVAL: usize = 64;
+
+#[wasm_bindgen]
+pub struct SearchSession {
+    board: [u64; 2],
+    rules: Variant,
+    history: GameHistory,
+    root: SearchNode,
+    rng: RngProvider,
+    stats: SearchStats,
+    elapsed_ms: f64,
+    policy: FinalPolicy,
+}
+
+#[wasm_bindgen]
+impl SearchSession {
+    // playouts are reproducible for a given `seed`, random if it is omitted
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: Option<u32>) -> SearchSession {
+        let history = GameHistory::new(DrawRules::default());
+        SearchSession::with_seed(&[0u64; 2], Variant::default(), history, seed.map(u64::from))
+    }
+
+    // discards the current tree and starts searching `sparse` for `turn`,
+    // the random sequence carries on; returns false, keeping the current
+    // search, if `sparse` is not an 8x8 board
+    pub fn start(&mut self, sparse: &[i32], turn: i32) -> bool {
+        self.start_with_history(sparse, turn, &[], &DrawRules::default())
+    }
+
+    // `start` on a `size` x `size` board, `sparse` has `size * size` entries;
+    // returns false, keeping the current search, if they do not match
+    pub fn start_sized(&mut self, sparse: &[i32], size: i32, turn: i32) -> bool {
+        self.start_variant(sparse, size, turn, "standard")
+    }
+
+    // `start_sized` under the rules of a named variant, see
+    // `Variant::from_name`; also false if the name is unknown
+    pub fn start_variant(&mut self, sparse: &[i32], size: i32, turn: i32, variant: &str) -> bool {
+        let rules = match Geometry::new(size).and_then(|geo| Variant::from_name(variant, geo)) {
+            Some(rules) => rules,
+            None => return false,
+        };
+        match rules.geometry().from_sparse(sparse, turn) {
+            Some(board) => {
+                self.restart(&board, rules, GameHistory::new(DrawRules::default()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // `start` with the positions played before, see `GameHistory::from_sparse`
+    pub fn start_with_history(
+        &mut self,
+        sparse: &[i32],
+        turn: i32,
+        history: &[i32],
+        rules: &DrawRules,
+    ) -> bool {
+        let variant = Variant::default();
+        let board = match variant.geometry().from_sparse(sparse, turn) {
+            Some(board) => board,
+            None => return false,
+        };
+        let history = GameHistory::from_sparse(history, variant.geometry(), turn, *rules);
+        self.restart(&board, variant, history);
+        true
+    }
+
+    // runs `iterations` more passes, returns the total so far
+    pub fn step(&mut self, iterations: u32) -> u32 {
+        let start = now_ms();
+        for _i in 0..iterations {
+            self.pass();
+        }
+        self.elapsed_ms += now_ms() - start;
+        self.stats.iterations
+    }
+
+    // searches for about `ms` milliseconds, returns the total so far
+    pub fn run_for(&mut self, ms: f64) -> u32 {
+        let start = now_ms();
+        loop {
+            for _i in 0..TIME_CHECK_INTERVAL {
+                self.pass();
+            }
+            if now_ms() - start >= ms {
+                break;
+            }
+        }
+        self.elapsed_ms += now_ms() - start;
+        self.stats.iterations
+    }
+
+    pub fn iterations(&self) -> u32 {
+        self.stats.iterations
+    }
+
+    pub fn visits(&self) -> f32 {
+        self.root.visits()
+    }
+
+    // the move the final move policy picks now, see `set_policy`
+    pub fn best_so_far(&mut self) -> Option<Move> {
+        self.root
+            .final_move(self.policy, &mut self.rng)
+            .map(|(src, dst)| Move::from_coords(src, dst))
+    }
+
+    // picks the final move policy by name, see `FinalPolicy::from_name`;
+    // returns false, keeping the current one, if the name is unknown. The
+    // policy carries over to new searches.
+    pub fn set_policy(&mut self, name: &str, param: f32) -> bool {
+        match FinalPolicy::from_name(name, param) {
+            Some(policy) => {
+                self.policy = policy;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // whether the highest value and the most visits are on the same move
+    pub fn agreed(&self) -> bool {
+        self.root.policies_agree()
+    }
+
+    // searches on until `agreed`, for at most `iterations` more passes;
+    // returns the total so far
+    pub fn run_until_agreed(&mut self, iterations: u32) -> u32 {
+        let start = now_ms();
+        for _i in 0..iterations {
+            if self.agreed() {
+                break;
+            }
+            self.pass();
+        }
+        self.elapsed_ms += now_ms() - start;
+        self.stats.iterations
+    }
+
+    // whether the root has had the iterations of `strength`, counting those
+    // spent on it while pondering
+    pub fn searched(&self, strength: &Strength) -> bool {
+        self.visits() >= (strength.iterations * SIMULATE_COUNT) as f32
+    }
+
+    // `best_so_far`, but chosen as a player of `strength` would, see
+    // `choose_move`
+    pub fn pick_move(&mut self, strength: &Strength) -> Option<Move> {
+        let result = self.result()?;
+        Some(choose_move(&result, strength, &mut self.rng))
+    }
+
+    // a move from the loaded opening book for the root position, if any;
+    // books are for the standard game only
+    pub fn book_move(&mut self) -> Option<Move> {
+        book::variant_book_move(&self.board, &self.rules, &mut self.rng)
+            .map(|(src, dst)| Move::from_coords(src, dst))
+    }
+
+    // plays `m` on the root, keeping the searched subtree below it; returns
+    // whether that subtree had been visited (a ponder hit). An illegal move
+    // leaves the session as it is and returns false.
+    pub fn play(&mut self, m: &Move) -> bool {
+        self.advance((m.x0, m.y0).to_coord(), (m.x1, m.y1).to_coord())
+    }
+
+    // plays the move that leads from the current root to `sparse`, adding
+    // the root to the game history; if there is none the search starts over
+    // under the same draw rules, with the positions in between unknown (see
+    // `start_with_history`). Returns whether this was a ponder hit, false if
+    // `sparse` is the current root.
+    pub fn sync(&mut self, sparse: &[i32], turn: i32) -> bool {
+        let board = match self.rules.geometry().from_sparse(sparse, turn) {
+            Some(board) => board,
+            None => return false,
+        };
+        if board == self.board {
+            return false;
+        }
+        let reply = self
+            .rules
+            .moves(&self.board)
+            .into_iter()
+            .find(|&(src, dst)| self.rules.apply_move(&self.board, src, dst) == board);
+        if let Some((src, dst)) = reply {
+            self.advance(src, dst)
+        } else {
+            let (rules, draws) = (self.rules, self.history.rules);
+            self.restart(&board, rules, GameHistory::new(draws));
+            false
+        }
+    }
+
+    // the tree searched so far down to `max_depth` plies, leaving out moves
+    // with fewer than `min_visits` visits, as a `TreeSnapshot` object
+    pub fn tree_json(&self, max_depth: u32, min_visits: f32) -> JsValue {
+        JsValue::from_serde(&self.snapshot(max_depth, min_visits)).unwrap()
+    }
+
+    // `tree_json` as a Graphviz digraph
+    pub fn tree_dot(&self, max_depth: u32, min_visits: f32) -> String {
+        self.snapshot(max_depth, min_visits).to_dot()
+    }
+
+    // the session as bytes, see `load`
+    pub fn save(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    // replaces the session with one from `save`, the search carrying on
+    // exactly as it would have; false and unchanged if the bytes are not a
+    // valid save
+    pub fn load(&mut self, bytes: &[u8]) -> bool {
+        match SearchSession::from_bytes(bytes) {
+            Ok(session) => {
+                *self = session;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    // where the search wants to move, as a `Heatmap` object with the `top`
+    // most visited moves
+    pub fn heatmap(&self, top: u32) -> JsValue {
+        JsValue::from_serde(&self.root.heatmap(top as usize)).unwrap()
+    }
+
+    // root visits per square moved from, indexed by x + 8 * y
+    pub fn source_visits(&self) -> Vec<f32> {
+        self.root.heatmap(0).source_visits
+    }
+
+    // mean value for the side to move per square moved from
+    pub fn source_values(&self) -> Vec<f32> {
+        self.root.heatmap(0).source_values
+    }
+
+    // root visits per square moved to
+    pub fn dest_visits(&self) -> Vec<f32> {
+        self.root.heatmap(0).dest_visits
+    }
+
+    // mean value for the side to move per square moved to
+    pub fn dest_values(&self) -> Vec<f32> {
+        self.root.heatmap(0).dest_values
+    }
+
+    // the `SearchResult` of the search so far, as a plain object; null if
+    // no move has been visited yet
+    pub fn finish(&mut self, verbose: bool) -> JsValue {
+        let result = match self.result() {
+            Some(result) => result,
+            None => return JsValue::NULL,
+        };
+        if verbose {
+            result.log();
+        }
+        JsValue::from_serde(&result).unwrap()
+    }
+}
+
+impl SearchSession {
+    pub fn with_seed(
+        board: &[u64; 2],
+        rules: Variant,
+        history: GameHistory,
+        seed: Option<u64>,
+    ) -> Self {
+        SearchSession::with_rng(board, rules, history, RngProvider::with_seed(seed))
+    }
+
+    fn with_rng(board: &[u64; 2], rules: Variant, history: GameHistory, rng: RngProvider) -> Self {
+        let root = SearchNode::root(board, &rules, &history);
+        SearchSession {
+            board: *board,
+            rules,
+            history,
+            root,
+            rng,
+            stats: SearchStats::default(),
+            elapsed_ms: 0f64,
+            policy: FinalPolicy::default(),
+        }
+    }
+
+    // a new search on `board`; the random sequence and the policy carry on
+    fn restart(&mut self, board: &[u64; 2], rules: Variant, history: GameHistory) {
+        let policy = self.policy;
+        *self = SearchSession::with_rng(board, rules, history, self.rng.clone());
+        self.policy = policy;
+    }
+
+    pub(crate) fn pass(&mut self) {
+        let pass = mcts_search_pass(
+            &mut self.root,
+            &self.board,
+            &self.rules,
+            &self.history,
+            &mut self.rng,
+            false,
+        );
+        self.stats.add(&pass);
+    }
+
+    // advances the root by one move, see `play`
+    pub fn advance(&mut self, src: i32, dst: i32) -> bool {
+        if !self.rules.moves(&self.board).contains(&(src, dst)) {
+            return false;
+        }
+        let child = match self.root.data {
+            Some((SearchNodeData::Mid { ref mut childs, .. }, ..)) => childs
+                .iter()
+                .position(|child| child.curr_move == (src, dst))
+                .map(|i| childs.swap_remove(i)),
+            _ => None,
+        };
+
+        let board = self.rules.apply_move(&self.board, src, dst);
+        self.history.push(&self.board);
+        self.board = board;
+        self.stats = SearchStats::default();
+        self.elapsed_ms = 0f64;
+
+        match child {
+            Some(mut child) if child.data.is_some() => {
+                child.curr_move = EMPTY_MOVE;
+                self.root = child;
+                self.root.solve_root(&board, &self.rules, &self.history);
+                self.root.screen_root(&board, &self.rules);
+                true
+            }
+            _ => {
+                self.root = SearchNode::root(&board, &self.rules, &self.history);
+                false
+            }
+        }
+    }
+
+    pub fn elapsed_ms(&self) -> f64 {
+        self.elapsed_ms
+    }
+
+    pub(crate) fn add_elapsed(&mut self, ms: f64) {
+        self.elapsed_ms += ms;
+    }
+
+    pub fn board(&self) -> &[u64; 2] {
+        &self.board
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        self.rules.geometry()
+    }
+
+    pub fn rules(&self) -> &Variant {
+        &self.rules
+    }
+
+    pub fn snapshot(&self, max_depth: u32, min_visits: f32) -> TreeSnapshot {
+        self.root.snapshot(&self.board, self.geometry(), max_depth, min_visits)
+    }
+
+    pub fn root(&self) -> &SearchNode {
+        &self.root
+    }
+
+    // None until a root move has been visited
+    pub fn result(&mut self) -> Option<SearchResult> {
+        let (stats, elapsed_ms) = (self.stats.clone(), self.elapsed_ms);
+        SearchResult::with_policy(&mut self.root, stats, elapsed_ms, self.policy, &mut self.rng)
+    }
+
+    // the position, the rules, the history, the random state, the stats,
+    // the policy and the whole tree, in the format of save.rs
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        save::write_header(&mut out);
+        save::write_str(&mut out, self.rules.name());
+        out.push(self.geometry().size() as u8);
+        save::write_board(&mut out, &self.board);
+        save::write_history(&mut out, &self.history);
+        for &word in self.rng.to_words().iter() {
+            book::write_le(&mut out, word, 8);
+        }
+        save::write_stats(&mut out, &self.stats);
+        book::write_le(&mut out, self.elapsed_ms.to_bits(), 8);
+        save::write_str(&mut out, self.policy.name());
+        save::write_f32(&mut out, self.policy.param());
+        save::write_node(&mut out, &self.root);
+        out
+    }
+
+    // checks every board of the tree against the saved rules
+    pub fn from_bytes(bytes: &[u8]) -> Result<SearchSession, TreeError> {
+        let mut reader = save::Reader::new(bytes);
+        save::read_header(&mut reader)?;
+        let name = reader.str()?;
+        let geo = Geometry::new(reader.u8()? as i32).ok_or(TreeError::BadField)?;
+        let rules = Variant::from_name(name, geo).ok_or(TreeError::BadField)?;
+        let board = reader.board()?;
+        if !geo.holds(&board) || board[0] & board[1] != 0 {
+            return Err(TreeError::BadBoard);
+        }
+        let history = save::read_history(&mut reader)?;
+        let mut words = [0u64; 6];
+        for word in words.iter_mut() {
+            *word = reader.u64()?;
+        }
+        let rng = RngProvider::from_words(&words).ok_or(TreeError::BadField)?;
+        let stats = save::read_stats(&mut reader)?;
+        let elapsed_ms = reader.f64()?;
+        let (name, param) = (reader.str()?, reader.f32()?);
+        let policy = FinalPolicy::from_name(name, param).ok_or(TreeError::BadField)?;
+        let root = save::read_node(&mut reader, &board, &rules, true)?;
+        if !reader.is_empty() {
+            return Err(TreeError::BadField);
+        }
+        Ok(SearchSession {
+            board,
+            rules,
+            history,
+            root,
+            rng,
+            stats,
+            elapsed_ms,
+            policy,
+        })
+    }
+
+    pub fn into_tree(self) -> (SearchNode, SearchStats) {
+        (self.root, self.stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> SearchSession {
+        let rules = Variant::default();
+        let board = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        SearchSession::with_seed(&board, rules, GameHistory::new(DrawRules::default()), Some(1))
+    }
+
+    #[test]
+    fn start_needs_an_8x8_board() {
+        let mut session = session();
+        session.step(16);
+        assert!(!session.start(&[-1; 36], 0));
+        assert_eq!(session.iterations(), 16);
+        let sparse = session.geometry().to_sparse(session.board(), 0);
+        assert!(session.start(&sparse, 0));
+        assert_eq!(session.iterations(), 0);
+    }
+
+    #[test]
+    fn sync_to_the_same_position_is_no_hit() {
+        let mut session = session();
+        session.step(64);
+        let sparse = session.geometry().to_sparse(session.board(), 0);
+        assert!(!session.sync(&sparse, 0));
+        assert!(session.visits() > 0f32);
+    }
+
+    #[test]
+    fn sync_one_move_on_is_a_hit() {
+        let mut session = session();
+        session.step(256);
+        let (src, dst) = session.root().most_visited().unwrap().curr_move;
+        let next = session.rules().apply_move(session.board(), src, dst);
+        let sparse = session.geometry().to_sparse(&next, 1);
+        assert!(session.sync(&sparse, 1));
+        assert_eq!(session.board(), &next);
+    }
+
+    #[test]
+    fn sync_keeps_the_game_history() {
+        let draws = DrawRules::new(2, 100, 0.3f32);
+        let mut session = session();
+        session.history = GameHistory::new(draws);
+        let first = session.rules().moves(session.board())[0];
+        let next = session.rules().apply_move(session.board(), first.0, first.1);
+        assert!(!session.sync(&session.geometry().to_sparse(&next, 1), 1));
+        assert_eq!(session.history.moves_played, 1);
+        assert_eq!(session.history.hashes.len(), 1);
+
+        // two moves on the positions in between are unknown
+        let (src, dst) = session.rules().moves(&next)[0];
+        let after = session.rules().apply_move(&next, src, dst);
+        let (src, dst) = session.rules().moves(&after)[0];
+        let later = session.rules().apply_move(&after, src, dst);
+        assert!(!session.sync(&session.geometry().to_sparse(&later, 1), 1));
+        assert_eq!(session.board(), &later);
+        assert_eq!(session.history.rules.move_limit, 100);
+        assert_eq!(session.history.moves_played, 0);
+    }
+
+    #[test]
+    fn save_load_continue() {
+        let mut straight = session();
+        straight.step(300);
+
+        let mut saved = session();
+        saved.step(120);
+        let mut loaded = SearchSession::from_bytes(&saved.to_bytes()).unwrap();
+        loaded.step(180);
+
+        // only the clock differs
+        straight.elapsed_ms = 0f64;
+        loaded.elapsed_ms = 0f64;
+        assert!(straight.to_bytes() == loaded.to_bytes());
+    }
+
+    #[test]
+    fn illegal_moves_are_not_played() {
+        let mut session = session();
+        session.step(64);
+        let board = *session.board();
+        // from an empty square, and a piece moved the wrong distance
+        let src = (3, 3).to_coord();
+        assert!(!session.advance(src, (3, 4).to_coord()));
+        assert!(!session.advance((1, 0).to_coord(), (1, 1).to_coord()));
+        assert_eq!(session.board(), &board);
+        assert_eq!(session.stats.iterations, 64);
+    }
+}
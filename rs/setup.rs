@@ -0,0 +1,167 @@
+use super::*;
+
+use notation::{parse_position, position_name};
+
+// start positions, all with black to move; the board is relative to black
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Setup {
+    // black along the top and bottom edges, white along the sides
+    Standard,
+    // the same squares, with the colours alternating along every edge
+    ScrambledEggs,
+    // as many pieces as the standard setup on random squares, white on the
+    // mirror image of black across the main diagonal
+    RandomSymmetric,
+    // a triangle in each corner, black in the top left and bottom right
+    Corners,
+}
+
+impl Setup {
+    pub fn from_name(name: &str) -> Option<Setup> {
+        match name.to_ascii_lowercase().as_str() {
+            "standard" => Some(Setup::Standard),
+            "scrambled-eggs" | "scrambled" => Some(Setup::ScrambledEggs),
+            "random-symmetric" | "random" => Some(Setup::RandomSymmetric),
+            "corners" => Some(Setup::Corners),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Setup::Standard => "standard",
+            Setup::ScrambledEggs => "scrambled-eggs",
+            Setup::RandomSymmetric => "random-symmetric",
+            Setup::Corners => "corners",
+        }
+    }
+
+    // `rng` is only used for random setups
+    pub fn board(self, geo: Geometry, rng: &mut RngProvider) -> [u64; 2] {
+        match self {
+            Setup::Standard => edges(geo, |_i| (0, 1)),
+            Setup::ScrambledEggs => edges(geo, |i| if i % 2 == 1 { (0, 1) } else { (1, 0) }),
+            Setup::RandomSymmetric => random_symmetric(geo, rng),
+            Setup::Corners => corners(geo),
+        }
+    }
+}
+
+// fills the edges but the corners; `ids(i)` gives the sides owning the `i`th
+// square along the top and bottom rows, and along the left and right columns
+fn edges<F: Fn(i32) -> (usize, usize)>(geo: Geometry, ids: F) -> [u64; 2] {
+    let last = geo.size() - 1;
+    let mut board = [0u64; 2];
+    for i in 1..last {
+        let (row, col) = ids(i);
+        board[row] |= (i, 0).to_coord().to_piece() | (i, last).to_coord().to_piece();
+        board[col] |= (0, i).to_coord().to_piece() | (last, i).to_coord().to_piece();
+    }
+    board
+}
+
+// triangles with legs of half the board less one, so they never touch
+fn corners(geo: Geometry) -> [u64; 2] {
+    let last = geo.size() - 1;
+    let legs = geo.size() / 2 - 1;
+    let mut board = [0u64; 2];
+    for y in 0..legs {
+        for x in 0..legs - y {
+            board[0] |= (x, y).to_coord().to_piece() | (last - x, last - y).to_coord().to_piece();
+            board[1] |= (last - x, y).to_coord().to_piece() | (x, last - y).to_coord().to_piece();
+        }
+    }
+    board
+}
+
+fn random_symmetric(geo: Geometry, rng: &mut RngProvider) -> [u64; 2] {
+    let count = 2 * (geo.size() - 2);
+    loop {
+        let mut board = [0u64; 2];
+        let mut placed = 0;
+        while placed < count {
+            let pos = rng.below(geo.squares() as u32) as i32;
+            let (x, y) = (pos % geo.size(), pos / geo.size());
+            let (black, white) = ((x, y).to_coord().to_piece(), (y, x).to_coord().to_piece());
+            if x == y || ((board[0] | board[1]) & (black | white)) != 0 {
+                continue;
+            }
+            board[0] |= black;
+            board[1] |= white;
+            placed += 1;
+        }
+        if board.is_win_state().is_none() {
+            return board;
+        }
+    }
+}
+
+// the sparse board of a named setup on a `size` x `size` board, empty if
+// either is unknown
+#[wasm_bindgen]
+pub fn start_position(name: &str, size: i32, seed: Option<u32>) -> Vec<i32> {
+    match (Setup::from_name(name), Geometry::new(size)) {
+        (Some(setup), Some(geo)) => {
+            let mut rng = RngProvider::with_seed(seed.map(u64::from));
+            geo.to_sparse(&setup.board(geo, &mut rng), 0)
+        }
+        _ => vec![],
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SparsePosition {
+    size: i32,
+    turn: i32,
+    sparse: Vec<i32>,
+}
+
+// `{ size, turn, sparse }` for a position string, see `position_name`;
+// throws the reason if it cannot be read
+#[wasm_bindgen]
+pub fn parse_position_string(text: &str) -> Result<JsValue, JsValue> {
+    let (geo, board, turn) = parse_position(text).map_err(|err| JsValue::from_str(&err))?;
+    Ok(JsValue::from_serde(&SparsePosition {
+        size: geo.size(),
+        turn,
+        sparse: geo.to_sparse(&board, turn),
+    })
+    .unwrap())
+}
+
+// the position string of `sparse` on a `size` x `size` board with `turn` to
+// move, empty if they do not match
+#[wasm_bindgen]
+pub fn position_string(sparse: &[i32], size: i32, turn: i32) -> String {
+    Geometry::new(size)
+        .and_then(|geo| Some((geo, geo.from_sparse(sparse, turn)?)))
+        .map_or_else(String::new, |(geo, board)| position_name(&board, geo, turn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_symmetric_is_symmetric() {
+        for size in 6..=8 {
+            let geo = Geometry::new(size).unwrap();
+            for seed in 0..20 {
+                let board = Setup::RandomSymmetric.board(geo, &mut RngProvider::from_seed(seed));
+                let mirror = (0..64).fold(0u64, |mirror, pos: i32| {
+                    let (x, y) = pos.to_coord_2d();
+                    if (board[0] & pos.to_piece()) != 0 {
+                        mirror | (y, x).to_coord().to_piece()
+                    } else {
+                        mirror
+                    }
+                });
+                assert_eq!(board[1], mirror);
+                assert_eq!(board[0].count_ones(), 2 * (size as u32 - 2));
+                assert_eq!(board[0] & board[1], 0);
+                assert_eq!((board[0] | board[1]) & !geo.area(), 0);
+                assert!(board.is_win_state().is_none());
+            }
+        }
+    }
+}
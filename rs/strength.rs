@@ -0,0 +1,381 @@
+use super::*;
+
+use rng::splitmix64;
+
+// matches between levels are cut off as draws after this many plies
+const MAX_MATCH_PLIES: u32 = 200;
+// scores are clamped to this far from 0 and 1 before being turned into Elo
+const MIN_SCORE: f32 = 0.01f32;
+
+// how strongly the engine plays: fewer iterations, a softmax over the root
+// visit counts and, now and then, the second best move on purpose
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Strength {
+    pub iterations: u32,
+    // 0 always plays the best move, higher spreads the choice over moves
+    // with fewer visits
+    pub temperature: f32,
+    // chance of playing the second best move instead
+    pub blunder_rate: f32,
+}
+
+#[wasm_bindgen]
+impl Strength {
+    #[wasm_bindgen(constructor)]
+    pub fn new(iterations: u32, temperature: f32, blunder_rate: f32) -> Strength {
+        Strength {
+            iterations,
+            temperature,
+            blunder_rate,
+        }
+    }
+}
+
+impl Default for Strength {
+    fn default() -> Self {
+        Level::Full.strength()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Beginner,
+    Casual,
+    Intermediate,
+    Advanced,
+    Full,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name.to_ascii_lowercase().as_str() {
+            "beginner" => Some(Level::Beginner),
+            "casual" => Some(Level::Casual),
+            "intermediate" => Some(Level::Intermediate),
+            "advanced" => Some(Level::Advanced),
+            "full" => Some(Level::Full),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Beginner => "beginner",
+            Level::Casual => "casual",
+            Level::Intermediate => "intermediate",
+            Level::Advanced => "advanced",
+            Level::Full => "full",
+        }
+    }
+
+    pub fn strength(self) -> Strength {
+        match self {
+            Level::Beginner => Strength::new(256, 0.5f32, 0.2f32),
+            Level::Casual => Strength::new(1024, 0.25f32, 0.1f32),
+            Level::Intermediate => Strength::new(4096, 0.1f32, 0.04f32),
+            Level::Advanced => Strength::new(8192, 0.1f32, 0.02f32),
+            Level::Full => Strength::new(MAX_NODE as u32, 0f32, 0f32),
+        }
+    }
+
+    // rough placeholders, beginner put at 1000: short `play_match` runs
+    // between neighbouring levels from the standard start, where casual
+    // scored 18 / 20 against beginner, intermediate 18 / 20 against casual,
+    // advanced 7 / 12 against intermediate and full 6 / 8 against advanced;
+    // far too few games to tell the upper levels apart, the ignored
+    // `neighbouring_levels` test plays longer matches to re-measure them
+    pub fn elo(self) -> i32 {
+        match self {
+            Level::Beginner => 1000,
+            Level::Casual => 1380,
+            Level::Intermediate => 1760,
+            Level::Advanced => 1820,
+            Level::Full => 2010,
+        }
+    }
+}
+
+// a uniform float in [0, 1)
+fn unit(rng: &mut RngProvider) -> f32 {
+    rng.below(1 << 16) as f32 / 65536f32
+}
+
+// the move to play from a finished search under `strength`; `result.best`
+// at full strength, and whenever it is a proven win. Moves proven to lose
+// are never picked in its place.
+pub fn choose_move(result: &SearchResult, strength: &Strength, rng: &mut RngProvider) -> Move {
+    let proven_win = result
+        .children
+        .iter()
+        .any(|child| child.mv == result.best && child.proven == Proven::Win);
+    if proven_win {
+        return result.best.clone();
+    }
+
+    // the best move first, then the others by visits
+    let mut ranked: Vec<&ChildStat> = result
+        .children
+        .iter()
+        .filter(|child| child.mv == result.best || child.proven != Proven::Loss)
+        .collect();
+    ranked.sort_by(|a, b| b.visits.partial_cmp(&a.visits).unwrap());
+    if let Some(i) = ranked.iter().position(|child| child.mv == result.best) {
+        let best = ranked.remove(i);
+        ranked.insert(0, best);
+    }
+    if ranked.len() < 2 {
+        return result.best.clone();
+    }
+
+    if strength.blunder_rate > 0f32 && unit(rng) < strength.blunder_rate {
+        return ranked[1].mv.clone();
+    }
+    if strength.temperature <= 0f32 {
+        return result.best.clone();
+    }
+
+    // visits relative to the most visited move, so the temperature does not
+    // depend on the iteration budget
+    let most = ranked.iter().fold(0f32, |most, child| most.max(child.visits));
+    let weights: Vec<f32> = ranked
+        .iter()
+        .map(|child| ((child.visits / most - 1f32) / strength.temperature).exp())
+        .collect();
+    let mut rn = unit(rng) * weights.iter().sum::<f32>();
+    for (child, weight) in ranked.iter().zip(weights.iter()) {
+        if rn < *weight {
+            return child.mv.clone();
+        }
+        rn -= weight;
+    }
+    result.best.clone()
+}
+
+// keeps the move choice from reusing the random sequence of the search
+const SIDE_SEED: u64 = 0x5bd1e9955bd1e995u64;
+
+// searches `board` with the budget of `strength` and picks a move from it;
+// None if the search visits no move
+pub fn play_with_strength<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    history: &GameHistory,
+    strength: &Strength,
+    seed: Option<u64>,
+) -> Option<Move> {
+    let mut rng = RngProvider::with_seed(seed.map(|seed| seed ^ SIDE_SEED));
+    let result = search(board, rules, history, strength.iterations as usize, seed, false)?;
+    Some(choose_move(&result, strength, &mut rng))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    // points per game, draws counting half
+    pub fn score(&self) -> f32 {
+        let games = self.wins + self.draws + self.losses;
+        (self.wins as f32 + self.draws as f32 / 2f32) / games.max(1) as f32
+    }
+
+    // the rating difference the score suggests
+    pub fn elo_difference(&self) -> f32 {
+        let score = self.score().max(MIN_SCORE).min(1f32 - MIN_SCORE);
+        -400f32 * (1f32 / score - 1f32).log10()
+    }
+}
+
+// plays `games` games of `a` against `b` from `board` (relative to the side
+// to move), swapping sides every game; the score is from `a`'s side
+pub fn play_match<R: Rules>(
+    board: &[u64; 2],
+    rules: &R,
+    a: &Strength,
+    b: &Strength,
+    games: u32,
+    seed: u64,
+) -> MatchScore {
+    let mut score = MatchScore::default();
+    let mut state = seed;
+
+    for game in 0..games {
+        let sides = if game % 2 == 0 { [a, b] } else { [b, a] };
+        let mut board = *board;
+        let mut history = GameHistory::new(DrawRules::default());
+        // Some(i) once the player of `sides[i]` has won
+        let mut winner = None;
+
+        for ply in 0..MAX_MATCH_PLIES {
+            let mover = ply as usize % 2;
+            if let Some(win) = rules.is_win_state(&board) {
+                winner = Some(if win { mover } else { 1 - mover });
+                break;
+            }
+            // passing is not modelled, a stuck side draws
+            if rules.moves(&board).is_empty()
+                || history.is_draw(&history.hashes, board.position_hash(0), 0)
+            {
+                break;
+            }
+
+            let seed = Some(splitmix64(&mut state));
+            let m = match play_with_strength(&board, rules, &history, sides[mover], seed) {
+                Some(m) => m,
+                None => break,
+            };
+            history.push(&board);
+            board = rules.apply_move(&board, (m.x0, m.y0).to_coord(), (m.x1, m.y1).to_coord());
+        }
+
+        // `a` plays first in even games
+        match winner {
+            Some(i) if i == game as usize % 2 => score.wins += 1,
+            Some(_) => score.losses += 1,
+            None => score.draws += 1,
+        }
+    }
+    score
+}
+
+// the strength of a named level, see `Level::from_name`
+#[wasm_bindgen]
+pub fn level_strength(name: &str) -> Option<Strength> {
+    Level::from_name(name).map(Level::strength)
+}
+
+// the approximate Elo of a named level, 0 if it is unknown
+#[wasm_bindgen]
+pub fn level_elo(name: &str) -> i32 {
+    Level::from_name(name).map_or(0, Level::elo)
+}
+
+// `my_plain_solution` played at a named level; None if the level is unknown
+#[wasm_bindgen]
+pub fn my_plain_solution_level(
+    turn: i32,
+    sparse: &[i32],
+    level: &str,
+    seed: Option<u32>,
+) -> Option<Move> {
+    let strength = Level::from_name(level)?.strength();
+    let rules = Standard::default();
+    let board = rules.geometry().from_sparse(sparse, turn)?;
+    let history = GameHistory::new(DrawRules::default());
+    let seed = seed.map(u64::from);
+    if let Some((src, dst)) = book::book_move(&board, &mut RngProvider::with_seed(seed)) {
+        return Some(Move::from_coords(src, dst));
+    }
+    play_with_strength(&board, &rules, &history, &strength, seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // games per pairing, and the `play_match` seed the numbers in
+    // `Level::elo` are to be measured with
+    const MATCH_GAMES: u32 = 200;
+    const MATCH_SEED: u64 = 11;
+
+    fn child(x1: i32, visits: f32, proven: Proven) -> ChildStat {
+        ChildStat {
+            mv: Move::from_coords((0, 0).to_coord(), (x1, 0).to_coord()),
+            visits,
+            value: 0.5f32,
+            proven,
+        }
+    }
+
+    fn result(children: Vec<ChildStat>) -> SearchResult {
+        SearchResult {
+            best: children[0].mv.clone(),
+            visits: children.iter().map(|child| child.visits).sum(),
+            win_rate: 0.5f32,
+            children,
+            pv: vec![],
+            iterations: 0,
+            hits: 0,
+            term_expansions: 0,
+            expand_depths: vec![],
+            simulate_depths: vec![],
+            undecided: 0,
+            elapsed_ms: 0f64,
+        }
+    }
+
+    // every move the weakest settings could pick from `result`
+    fn choices(result: &SearchResult) -> Vec<Move> {
+        let strength = Strength::new(1, 100f32, 0.5f32);
+        let mut rng = RngProvider::from_seed(5);
+        let mut choices: Vec<Move> = vec![];
+        for _i in 0..200 {
+            let mv = choose_move(result, &strength, &mut rng);
+            if !choices.contains(&mv) {
+                choices.push(mv);
+            }
+        }
+        choices
+    }
+
+    #[test]
+    fn proven_wins_are_always_played() {
+        let result = result(vec![
+            child(1, 10f32, Proven::Win),
+            child(2, 9f32, Proven::Unknown),
+            child(3, 8f32, Proven::Unknown),
+        ]);
+        assert_eq!(choices(&result), vec![result.best.clone()]);
+    }
+
+    #[test]
+    fn proven_losses_are_never_played() {
+        let result = result(vec![
+            child(1, 10f32, Proven::Unknown),
+            child(2, 9f32, Proven::Loss),
+            child(3, 8f32, Proven::Unknown),
+            child(4, 7f32, Proven::Loss),
+        ]);
+        let choices = choices(&result);
+        assert_eq!(choices.len(), 2);
+        assert!(choices.iter().all(|mv| mv.x1 == 1 || mv.x1 == 3));
+    }
+
+    // slow, run with `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn neighbouring_levels() {
+        let rules = Standard::default();
+        let board = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        let levels = [
+            Level::Beginner,
+            Level::Casual,
+            Level::Intermediate,
+            Level::Advanced,
+            Level::Full,
+        ];
+        for pair in levels.windows(2) {
+            let (weaker, stronger) = (pair[0], pair[1]);
+            let score = play_match(
+                &board,
+                &rules,
+                &stronger.strength(),
+                &weaker.strength(),
+                MATCH_GAMES,
+                MATCH_SEED,
+            );
+            println!(
+                "{} against {}: {:?}, {:+.0} Elo measured, {:+} in `Level::elo`",
+                stronger.name(),
+                weaker.name(),
+                score,
+                score.elo_difference(),
+                stronger.elo() - weaker.elo(),
+            );
+        }
+    }
+}
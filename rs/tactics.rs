@@ -0,0 +1,88 @@
+use super::*;
+
+use puzzle::{forced_win, wins_by};
+
+impl SearchNode {
+    // settles the root moves a single ply decides, which a few noisy playouts
+    // can miss: a move that wins at once is marked won, and the search has
+    // nothing left to do; otherwise every move after which the opponent wins
+    // at once is marked lost, and `find_max` passes over it
+    pub fn screen_root<R: Rules>(&mut self, board: &[u64; 2], rules: &R) {
+        let (childs, a, b) = match self.data {
+            Some((SearchNodeData::Mid { ref mut childs, .. }, ref mut a, ref mut b)) => {
+                (childs, a, b)
+            }
+            _ => return,
+        };
+
+        let win = childs.iter_mut().find(|child| {
+            let (src, dst) = child.curr_move;
+            wins_by(board, rules, src, dst, 1)
+        });
+        if let Some(child) = win {
+            // lost for the side to move after it, as in `solve_root`
+            child.data = Some((SearchNodeData::Term(false), 0f32, 1f32));
+            if *b == 0f32 {
+                *a = 1f32;
+                *b = 1f32;
+            }
+            return;
+        }
+
+        for child in childs.iter_mut() {
+            let (src, dst) = child.curr_move;
+            if forced_win(&rules.apply_move(board, src, dst), rules, 1) {
+                let visits = child.visits().max(1f32);
+                child.data = Some((SearchNodeData::Term(true), visits, visits));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(curr_move: (i32, i32), data: SearchNodeData, a: f32, b: f32) -> SearchNode {
+        SearchNode {
+            curr_move,
+            data: Some((data, a, b)),
+        }
+    }
+
+    fn mid(childs: Vec<SearchNode>) -> SearchNodeData {
+        SearchNodeData::Mid {
+            curr: 0,
+            full: true,
+            board: [0, 0],
+            childs,
+        }
+    }
+
+    #[test]
+    fn lost_moves_are_passed_over() {
+        // the lost move has the better playouts, as a noisy search might give it
+        let mut root = node(
+            (-1, -1),
+            mid(vec![
+                node((0, 1), SearchNodeData::Term(true), 0f32, 40f32),
+                node((0, 2), mid(vec![]), 30f32, 40f32),
+            ]),
+            50f32,
+            80f32,
+        );
+        for &bare in [true, false].iter() {
+            let best = root.find_max(bare).map(|ptr| unsafe { (*ptr).curr_move });
+            assert_eq!(best, Some((0, 2)));
+        }
+
+        // with nothing else left the lost move is still played
+        let mut root = node(
+            (-1, -1),
+            mid(vec![node((0, 1), SearchNodeData::Term(true), 40f32, 40f32)]),
+            0f32,
+            40f32,
+        );
+        assert_eq!(root.find_max(true).map(|ptr| unsafe { (*ptr).curr_move }), Some((0, 1)));
+    }
+}
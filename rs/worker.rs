@@ -0,0 +1,211 @@
+use super::*;
+
+use wasm_bindgen::JsCast;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = Atomics, js_name = load)]
+    fn atomics_load(array: &JsValue, index: u32) -> i32;
+
+    pub type Callback;
+
+    #[wasm_bindgen(method, js_name = call)]
+    fn call0(this: &Callback, ctx: &JsValue) -> JsValue;
+
+    #[wasm_bindgen(method, js_name = call)]
+    fn call1(this: &Callback, ctx: &JsValue, arg: &JsValue) -> JsValue;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    pub best: Option<Move>,
+    pub visits: f32,
+    pub win_rate: f32,
+    pub iterations: u32,
+    pub elapsed_ms: f64,
+}
+
+pub trait SearchControl {
+    fn should_stop(&mut self) -> bool;
+    fn report(&mut self, progress: &Progress);
+}
+
+// `stop` is either an `Int32Array` whose first element is set to non-zero
+// (usually over a `SharedArrayBuffer`, polled with `Atomics.load`) or a
+// function returning true; `progress` is called with a `Progress` object.
+struct JsControl<'a> {
+    stop: &'a JsValue,
+    progress: &'a JsValue,
+}
+
+impl<'a> SearchControl for JsControl<'a> {
+    fn should_stop(&mut self) -> bool {
+        if self.stop.is_undefined() || self.stop.is_null() {
+            false
+        } else if self.stop.is_function() {
+            let stop: &Callback = self.stop.unchecked_ref();
+            stop.call0(&JsValue::NULL).as_bool().unwrap_or(false)
+        } else {
+            atomics_load(self.stop, 0) != 0
+        }
+    }
+
+    fn report(&mut self, progress: &Progress) {
+        if self.progress.is_function() {
+            let callback: &Callback = self.progress.unchecked_ref();
+            callback.call1(&JsValue::NULL, &JsValue::from_serde(progress).unwrap());
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SearchSession {
+    // blocking search meant to run inside a Web Worker: runs until `budget`
+    // iterations are done, `max_ms` milliseconds passed (0 disables either)
+    // or `stop` is set, reporting progress about every `report_ms`. With
+    // neither limit set it returns at once without searching.
+    pub fn run_worker(
+        &mut self,
+        budget: u32,
+        max_ms: f64,
+        report_ms: f64,
+        stop: &JsValue,
+        progress: &JsValue,
+    ) -> Option<Move> {
+        self.run_controlled(budget, max_ms, report_ms, &mut JsControl { stop, progress });
+        self.best_so_far()
+    }
+}
+
+impl SearchSession {
+    // the most visited move stands in for `best_so_far`, which may draw from
+    // the random sequence and so change the rest of the search
+    pub fn progress(&self) -> Progress {
+        let root = self.root();
+        Progress {
+            best: root.most_visited().map(|child| {
+                let (src, dst) = child.curr_move;
+                Move::from_coords(src, dst)
+            }),
+            visits: root.visits(),
+            win_rate: root.win_rate(),
+            iterations: self.iterations(),
+            elapsed_ms: self.elapsed_ms(),
+        }
+    }
+
+    // see `run_worker`, returns the total iterations so far
+    pub fn run_controlled<C: SearchControl>(
+        &mut self,
+        budget: u32,
+        max_ms: f64,
+        report_ms: f64,
+        control: &mut C,
+    ) -> u32 {
+        if budget == 0 && max_ms == 0f64 {
+            return self.iterations();
+        }
+        let start = now_ms();
+        let mut last = start;
+        let mut last_report = start;
+        loop {
+            // no further than the budget between two clock reads
+            let chunk = if budget == 0 {
+                TIME_CHECK_INTERVAL as u32
+            } else {
+                budget.saturating_sub(self.iterations()).min(TIME_CHECK_INTERVAL as u32)
+            };
+            for _i in 0..chunk {
+                self.pass();
+            }
+
+            let now = now_ms();
+            self.add_elapsed(now - last);
+            last = now;
+
+            let done = (budget != 0 && self.iterations() >= budget)
+                || (max_ms != 0f64 && now - start >= max_ms)
+                || control.should_stop();
+            if done || now - last_report >= report_ms {
+                control.report(&self.progress());
+                last_report = now;
+            }
+            if done {
+                break;
+            }
+        }
+        self.iterations()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // never stops on its own, counts the reports
+    struct Counter {
+        reports: u32,
+    }
+
+    impl SearchControl for Counter {
+        fn should_stop(&mut self) -> bool {
+            false
+        }
+
+        fn report(&mut self, _progress: &Progress) {
+            self.reports += 1;
+        }
+    }
+
+    fn session() -> SearchSession {
+        let rules = Variant::default();
+        let board = setup::Setup::Standard.board(rules.geometry(), &mut RngProvider::from_seed(0));
+        SearchSession::with_seed(&board, rules, GameHistory::new(DrawRules::default()), Some(1))
+    }
+
+    #[test]
+    fn no_limits_is_nothing_to_do() {
+        let mut session = session();
+        let mut control = Counter { reports: 0 };
+        assert_eq!(session.run_controlled(0, 0f64, 0f64, &mut control), 0);
+        assert_eq!(control.reports, 0);
+    }
+
+    #[test]
+    fn stops_at_the_budget() {
+        let mut session = session();
+        let mut control = Counter { reports: 0 };
+        assert_eq!(session.run_controlled(100, 0f64, 1e9f64, &mut control), 100);
+        assert_eq!(control.reports, 1);
+        // the budget counts the iterations already run
+        session.step(10);
+        assert_eq!(session.run_controlled(150, 0f64, 1e9f64, &mut control), 150);
+        assert_eq!(session.run_controlled(150, 0f64, 1e9f64, &mut control), 150);
+    }
+
+    #[test]
+    fn reports_leave_the_search_alone() {
+        let run = |report_ms| {
+            let mut session = session();
+            // a policy that draws from the random sequence
+            assert!(session.set_policy("proportional", 1f32));
+            let mut control = Counter { reports: 0 };
+            session.run_controlled(300, 0f64, report_ms, &mut control);
+            let visits: Vec<f32> =
+                session.result().unwrap().children.iter().map(|child| child.visits).collect();
+            (control.reports, visits, session.best_so_far())
+        };
+        let (often, visits, best) = run(0f64);
+        assert!(often > 1);
+        let (once, other_visits, other_best) = run(1e9f64);
+        assert_eq!(once, 1);
+        assert_eq!((visits, best), (other_visits, other_best));
+    }
+
+    #[test]
+    fn progress_before_any_visit() {
+        let progress = session().progress();
+        assert_eq!((progress.visits, progress.win_rate), (0f32, 0.5f32));
+        assert!(progress.best.is_none());
+    }
+}
@@ -1,27 +1,113 @@
 import { Bot } from "../glob/bot";
-import { my_plain_solution } from "../../pkg/ai_frontend";
+import { SearchSession, Strength, level_strength } from "../../pkg/ai_frontend";
 const treeify = require("treeify")
 
+const SliceIterations = 1024
+
 export class PlainBot extends Bot {
 
+	private readonly session = new SearchSession()
+	private pondering = false
+
+	// the budget `my_plain_solution` searches with, see `Level::Full`
+	protected readonly strength: Strength = level_strength("full")!
+
 	makeMove(board: number[]): Promise<{ x0: number, y0: number, x1: number, y1: number }> {
 
+		this.pondering = false
+
 		return new Promise((resolve, reject) => {
 
-			const res = my_plain_solution(this.turn, Int32Array.from(board))
+			this.session.sync(Int32Array.from(board), this.turn)
 
 			// console.log(this.turn, Int32Array.from(board))
 
-			resolve({ x0: res.x0, y0: res.y0, x1: res.x1, y1: res.y1 })
+			const book = this.session.book_move()
+
+			if (book) {
+
+				this.session.play(book)
+				resolve({ x0: book.x0, y0: book.y0, x1: book.x1, y1: book.y1 })
+				return
+
+			}
+
+			// search in slices so that rendering and timers keep running,
+			// visits gathered while pondering count towards the budget
+			const slice = () => {
+
+				if (this.searching()) {
+
+					setTimeout(slice, 0)
+
+				} else {
+
+					const res = this.session.pick_move(this.strength)
+
+					if (!res) {
+
+						reject(new Error("no move to play"))
+						return
+
+					}
+
+					this.session.play(res)
+
+					this.pondering = true
+					setTimeout(() => this.ponder(), 0)
+
+					resolve({ x0: res.x0, y0: res.y0, x1: res.x1, y1: res.y1 })
+
+				}
+
+			}
+
+			slice()
 
 		})
 
 	}
 
+	// keeps searching the position after our move until the reply arrives
+	private ponder() {
+
+		if (this.pondering && this.searching()) {
+
+			setTimeout(() => this.ponder(), 0)
+
+		}
+
+	}
+
+	// searches one more slice unless the budget is spent; false once it is,
+	// or once the visits stop growing as the root is solved or the game over
+	private searching(): boolean {
+
+		if (this.session.searched(this.strength)) {
+
+			return false
+
+		}
+
+		const visits = this.session.visits()
+		this.session.step(SliceIterations)
+		return this.session.visits() > visits
+
+	}
+
 }
 
 Bot.register("plain bot", PlainBot)
 
+// see `Level` on the Rust side for the other levels
+export class BeginnerBot extends PlainBot {
+
+	protected readonly strength: Strength = level_strength("beginner")!
+
+}
+
+Bot.register("beginner bot", BeginnerBot)
+
 window["log"] = console.log
 window["log_tree"] = (obj: any) => {
 	console.log(treeify.asTree(obj, true))
@@ -21,7 +21,9 @@ export const Config = {
 		Height: 24
 	},
 	Step: 60,
-	MoveAnimTime: 350
+	MoveAnimTime: 350,
+	// "standard", "scrambled-eggs" or "random-symmetric"
+	Setup: "standard"
 }
 
 export const Resources = {
@@ -2,6 +2,7 @@ import { Config, Resources, Game } from "../glob/global";
 import { Piece, PieceType } from "./piece";
 import { Vector, Actor, Sprite, EasingFunctions } from "excalibur";
 import { GameLogic } from "../glob/gamelogic";
+import { Move, is_legal_move, legal_destinations, start_position } from "../../pkg/ai_frontend";
 
 const Dim = 8
 
@@ -267,80 +268,36 @@ export class Grid extends Actor {
 
 	}
 
-	checkValid(x0: number, y0: number, x1: number, y1: number) {
+	toSparse(): number[] {
 
-		const dx = x1 - x0
-		const dy = y1 - y0
-
-		const adx = Math.abs(dx)
-		const ady = Math.abs(dy)
-
-		const idx = Math.sign(dx)
-		const idy = Math.sign(dy)
-
-		if (
-			x0 >= 0 && y0 >= 0 && x1 >= 0 && y1 >= 0 &&
-			x0 < Dim && y0 < Dim && x1 < Dim && y1 < Dim
-		) {
-
-			if (
-				(!!dx || !!dy) && ((!dx || !dy) || (adx == ady))
-			) {
-				if (
-					this.getLine(x0, y0, idx, idy) == Math.max(adx, ady)
-				) {
-					const p = this.getCell(x0, y0).piece
-					const q = this.getCell(x1, y1).piece
-
-					if (
-						(!!p && p.type == this.turn) &&
-						(!q || q.type != this.turn)
-					) {
-
-						for (
-							let x = x0 + idx, y = y0 + idy;
-							x != x1 || y != y1;
-							x += idx, y += idy
-						) {
-
-							const c = this.getCell(x, y)
-							if (!!c.piece && c.piece.type != this.turn) {
-								return false
-							}
-						}
-
-						return true
-					}
+		const board = new Array(Dim * Dim).fill(-1)
+
+		for (let i = 0; i < Dim; ++i) {
+			for (let j = 0; j < Dim; ++j) {
+				const p = this.getCell(i, j).piece
+				if (!!p) {
+					board[i + j * Dim] = p.type
 				}
 			}
 		}
 
-		return false
+		return board
 
 	}
 
-	calcMoves(x0: number, y0: number): { x: number, y: number }[] {
-
-		const res: any[] = []
-
-		const dxdy = [
-			[1, 0], [-1, 0], [0, 1], [0, -1],
-			[1, 1], [1, -1], [-1, -1], [-1, 1]
-		]
-
-		for (const [idx, idy] of dxdy) {
+	checkValid(x0: number, y0: number, x1: number, y1: number) {
 
-			const d = this.getLine(x0, y0, idx, idy)
+		const move = new Move(x0, y0, x1, y1)
+		const res = is_legal_move(this.turn, Int32Array.from(this.toSparse()), move)
+		move.free()
 
-			let x = x0 + d * idx, y = y0 + d * idy
+		return res
 
-			if (this.checkValid(x0, y0, x, y)) {
-				res.push({ x, y })
-			}
+	}
 
-		}
+	calcMoves(x0: number, y0: number): { x: number, y: number }[] {
 
-		return res
+		return legal_destinations(this.turn, Int32Array.from(this.toSparse()), x0, y0)
 
 	}
 
@@ -456,25 +413,12 @@ export class Grid extends Actor {
 
 			}
 
-			const w = PieceType.White
-			const b = PieceType.Black
-			const e: undefined = undefined
-
-			const board = [
-				e, b, b, b, b, b, b, e,
-				w, e, e, e, e, e, e, w,
-				w, e, e, e, e, e, e, w,
-				w, e, e, e, e, e, e, w,
-				w, e, e, e, e, e, e, w,
-				w, e, e, e, e, e, e, w,
-				w, e, e, e, e, e, e, w,
-				e, b, b, b, b, b, b, e,
-			]
+			const board = start_position(Config.Setup, Dim, undefined)
 
 			for (let i = 0; i != Dim; ++i) {
 				for (let j = 0; j != Dim; ++j) {
-					const type = board[j + i * Dim]
-					if (type !== undefined) {
+					const type: PieceType = board[j + i * Dim]
+					if (type >= 0) {
 
 						const piece = new Piece(type)
 						this.setCell(j, i, piece)